@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The last successful responses from Hack Club Mail, kept on disk so there's something to show
+/// when it can't be reached.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// A response as it was saved.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub cached_at: DateTime<Utc>,
+    pub body: Value,
+}
+
+impl CachedResponse {
+    /// How long ago the response was saved.
+    pub fn age(&self) -> Duration {
+        (Utc::now() - self.cached_at).to_std().unwrap_or_default()
+    }
+}
+
+impl Cache {
+    /// Keeps responses in `dir`, which is created when the first one is saved.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The saved response for `url`. Missing and unreadable ones are both `None`.
+    pub fn get(&self, url: &str) -> Option<CachedResponse> {
+        let data = std::fs::read(self.path(url)).ok()?;
+        serde_json::from_slice::<CachedResponse>(&data)
+            .ok()
+            .filter(|cached| cached.url == url)
+    }
+
+    /// Saves `body` as the latest response for `url`.
+    pub fn put(&self, url: &str, body: &Value) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.dir)?;
+        let cached = CachedResponse {
+            url: url.to_string(),
+            cached_at: Utc::now(),
+            body: body.clone(),
+        };
+        // written next to it first so a crash can't leave half a file behind
+        let path = self.path(url);
+        let partial = path.with_extension("json.partial");
+        std::fs::write(&partial, serde_json::to_vec(&cached)?)?;
+        std::fs::rename(partial, path)
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let url = url
+            .split_once("://")
+            .map_or(url, |(_, rest)| rest)
+            .trim_end_matches('/');
+        let name: String = url
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{name}.json"))
+    }
+}
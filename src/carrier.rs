@@ -0,0 +1,233 @@
+use serde::Serialize;
+use std::fmt;
+use std::ops::RangeInclusive;
+use url::Url;
+
+/// The shipping companies orphy can recognize tracking numbers of.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Carrier {
+    Usps,
+    Ups,
+    Fedex,
+    Dhl,
+    CanadaPost,
+    RoyalMail,
+    Asendia,
+}
+
+impl Carrier {
+    /// Works out who a tracking number belongs to from its format, checking the check digit of
+    /// formats that have one. Spaces and dashes are ignored.
+    ///
+    /// - UPS: `1Z` numbers.
+    /// - USPS: 20 and 22 digit numbers (22 starting with `91` to `95`), also with the `420` and zip
+    ///   code routing prefix scanned off the barcode.
+    /// - FedEx: 12 digit Express, 15 digit Ground and 22 digit `96` numbers.
+    /// - DHL: 10 digit Express waybills, `JJD` parcel and `GM` eCommerce numbers.
+    /// - Canada Post: 16 digit numbers.
+    /// - Asendia: its own `AS` references.
+    /// - International (UPU S10) numbers like `RA123456785US` go to the post of the country at
+    ///   the end: USPS for `US`, Canada Post for `CA` and Royal Mail for `GB`.
+    pub fn detect(tracking_number: &str) -> Option<Carrier> {
+        let number = normalize(tracking_number);
+        if number.len() < 10 || !number.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+            return None;
+        }
+
+        if number.len() == 18 && number.starts_with("1Z") {
+            return ups_check(&number).then_some(Carrier::Ups);
+        }
+        if let Some(country) = s10_country(&number) {
+            return match country {
+                "US" => Some(Carrier::Usps),
+                "CA" => Some(Carrier::CanadaPost),
+                "GB" => Some(Carrier::RoyalMail),
+                _ => None,
+            };
+        }
+        if !is_digits(&number) {
+            let dhl = |prefix: &str, lengths: RangeInclusive<usize>| {
+                number
+                    .strip_prefix(prefix)
+                    .is_some_and(|rest| lengths.contains(&rest.len()) && is_digits(rest))
+            };
+            if dhl("JJD", 10..=20) || dhl("GM", 16..=22) {
+                return Some(Carrier::Dhl);
+            }
+            return number.starts_with("AS").then_some(Carrier::Asendia);
+        }
+
+        // the barcode on a USPS label starts with 420 and the zip code the mail is going to
+        let number = match number.len() {
+            30 | 34 if number.starts_with("420") => &number[number.len() - 22..],
+            _ => &number,
+        };
+        match number.len() {
+            22 if number.starts_with("96") => mod10(&number[7..]).then_some(Carrier::Fedex),
+            22 if matches!(&number[..2], "91" | "92" | "93" | "94" | "95") => {
+                mod10(number).then_some(Carrier::Usps)
+            }
+            20 => mod10(number).then_some(Carrier::Usps),
+            16 => Some(Carrier::CanadaPost),
+            15 => mod10(number).then_some(Carrier::Fedex),
+            12 => fedex_express_check(number).then_some(Carrier::Fedex),
+            10 => dhl_check(number).then_some(Carrier::Dhl),
+            _ => None,
+        }
+    }
+
+    /// Works out the carrier from a tracking link the api gave, by the site it points to.
+    pub fn from_link(link: &str) -> Option<Carrier> {
+        let url = Url::parse(link).ok()?;
+        let host = url.host_str()?.to_lowercase();
+        let on = |domain: &str| host == domain || host.ends_with(&format!(".{domain}"));
+        if on("usps.com") {
+            Some(Carrier::Usps)
+        } else if on("ups.com") {
+            Some(Carrier::Ups)
+        } else if on("fedex.com") {
+            Some(Carrier::Fedex)
+        } else if on("dhl.com") || on("dhl.de") {
+            Some(Carrier::Dhl)
+        } else if on("canadapost-postescanada.ca") || on("canadapost.ca") {
+            Some(Carrier::CanadaPost)
+        } else if on("royalmail.com") {
+            Some(Carrier::RoyalMail)
+        } else if on("asendia.com") || on("asendiausa.com") {
+            Some(Carrier::Asendia)
+        } else {
+            None
+        }
+    }
+
+    /// The carrier's name, e.g. `Canada Post`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Carrier::Usps => "USPS",
+            Carrier::Ups => "UPS",
+            Carrier::Fedex => "FedEx",
+            Carrier::Dhl => "DHL",
+            Carrier::CanadaPost => "Canada Post",
+            Carrier::RoyalMail => "Royal Mail",
+            Carrier::Asendia => "Asendia",
+        }
+    }
+
+    /// The page on the carrier's site that tracks `tracking_number`.
+    pub fn tracking_url(self, tracking_number: &str) -> String {
+        let number = normalize(tracking_number);
+        match self {
+            Carrier::Usps => {
+                format!("https://tools.usps.com/go/TrackConfirmAction?tLabels={number}")
+            }
+            Carrier::Ups => format!("https://www.ups.com/track?tracknum={number}"),
+            Carrier::Fedex => format!("https://www.fedex.com/fedextrack/?trknbr={number}"),
+            Carrier::Dhl => {
+                format!("https://www.dhl.com/global-en/home/tracking.html?tracking-id={number}")
+            }
+            Carrier::CanadaPost => format!(
+                "https://www.canadapost-postescanada.ca/track-reperage/en#/search?searchFor={number}"
+            ),
+            Carrier::RoyalMail => {
+                format!("https://www.royalmail.com/track-your-item#/tracking-results/{number}")
+            }
+            Carrier::Asendia => format!("https://tracking.asendia.com/tracking/{number}"),
+        }
+    }
+}
+
+impl fmt::Display for Carrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A tracking number without the spaces and dashes it's often written with, in upper case.
+pub fn normalize(tracking_number: &str) -> String {
+    tracking_number
+        .chars()
+        .filter(|char| !char.is_whitespace() && *char != '-')
+        .collect::<String>()
+        .to_uppercase()
+}
+
+fn is_digits(text: &str) -> bool {
+    text.bytes().all(|byte| byte.is_ascii_digit())
+}
+
+fn digit(byte: u8) -> u32 {
+    u32::from(byte - b'0')
+}
+
+/// The GS1 check: from the right, leaving out the check digit, digits are weighted 3, 1, 3, ...
+fn mod10(number: &str) -> bool {
+    let (body, check) = number.split_at(number.len() - 1);
+    let sum: u32 = body
+        .bytes()
+        .rev()
+        .zip([3, 1].into_iter().cycle())
+        .map(|(byte, weight)| digit(byte) * weight)
+        .sum();
+    (10 - sum % 10) % 10 == digit(check.as_bytes()[0])
+}
+
+/// FedEx Express numbers weight their first 11 digits 3, 1, 7, ... and take the sum mod 11.
+fn fedex_express_check(number: &str) -> bool {
+    let sum: u32 = number[..11]
+        .bytes()
+        .zip([3, 1, 7].into_iter().cycle())
+        .map(|(byte, weight)| digit(byte) * weight)
+        .sum();
+    sum % 11 % 10 == digit(number.as_bytes()[11])
+}
+
+/// DHL Express waybills end with the rest of the first 9 digits divided by 7.
+fn dhl_check(number: &str) -> bool {
+    number[..9]
+        .parse::<u64>()
+        .is_ok_and(|body| body % 7 == u64::from(digit(number.as_bytes()[9])))
+}
+
+/// UPS numbers turn the letters after `1Z` into digits and weight them 1, 2, 1, ...
+fn ups_check(number: &str) -> bool {
+    let mut values = Vec::new();
+    for byte in number[2..].bytes() {
+        match byte {
+            b'0'..=b'9' => values.push(digit(byte)),
+            b'A'..=b'Z' => values.push(u32::from(byte - b'A' + 2) % 10),
+            _ => return false,
+        }
+    }
+    let check = values.pop().unwrap_or_default();
+    let sum: u32 = values
+        .iter()
+        .zip([1, 2].into_iter().cycle())
+        .map(|(value, weight)| value * weight)
+        .sum();
+    (10 - sum % 10) % 10 == check
+}
+
+/// The country of a UPU S10 number (two letters, eight digits, a check digit and the country),
+/// if it is one and the check digit is right.
+fn s10_country(number: &str) -> Option<&str> {
+    let bytes = number.as_bytes();
+    if bytes.len() != 13
+        || !bytes[..2].iter().all(u8::is_ascii_uppercase)
+        || !bytes[2..11].iter().all(u8::is_ascii_digit)
+        || !bytes[11..].iter().all(u8::is_ascii_uppercase)
+    {
+        return None;
+    }
+    let sum: u32 = bytes[2..10]
+        .iter()
+        .zip([8, 6, 4, 2, 3, 5, 9, 7])
+        .map(|(byte, weight)| digit(*byte) * weight)
+        .sum();
+    let check = match 11 - sum % 11 {
+        10 => 0,
+        11 => 5,
+        check => check,
+    };
+    (check == digit(bytes[10])).then(|| &number[11..])
+}
@@ -0,0 +1,99 @@
+use crate::mail::{Event, Letter};
+use serde::Serialize;
+
+/// Something that happened to a piece of mail between two looks at it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Change {
+    /// Mail that wasn't there before.
+    NewLetter { letter: Letter },
+    /// The status of a piece of mail changed.
+    StatusChanged {
+        letter: Letter,
+        from: Option<String>,
+        to: Option<String>,
+    },
+    /// A tracking event was appended to a piece of mail.
+    NewEvent { letter: Letter, event: Event },
+}
+
+impl Change {
+    /// The mail this change is about.
+    pub fn letter(&self) -> &Letter {
+        match self {
+            Change::NewLetter { letter }
+            | Change::StatusChanged { letter, .. }
+            | Change::NewEvent { letter, .. } => letter,
+        }
+    }
+
+    /// The kind of change as it's serialized, e.g. `new_event`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Change::NewLetter { .. } => "new_letter",
+            Change::StatusChanged { .. } => "status_changed",
+            Change::NewEvent { .. } => "new_event",
+        }
+    }
+
+    /// Describes the change in a few words for tables and notifications.
+    pub fn describe(&self) -> String {
+        match self {
+            Change::NewLetter { letter } => format!(
+                "new {} ({})",
+                letter.letter_type.as_deref().unwrap_or("mail"),
+                letter.status.as_deref().unwrap_or("no status")
+            ),
+            Change::StatusChanged { from, to, .. } => format!(
+                "{} -> {}",
+                from.as_deref().unwrap_or("no status"),
+                to.as_deref().unwrap_or("no status")
+            ),
+            Change::NewEvent { event, .. } => {
+                let mut description = event
+                    .description
+                    .clone()
+                    .unwrap_or_else(|| String::from("new event"));
+                if let Some(place) = event.facility.as_ref().or(event.location.as_ref()) {
+                    description += &format!(" at {place}");
+                }
+                if let Some(happened_at) = event.happened_at {
+                    description += &format!(" ({})", happened_at.naive_utc());
+                }
+                description
+            }
+        }
+    }
+}
+
+/// Works out what changed between the last known state of a piece of mail and the current one.
+///
+/// Events are only compared when `previous` has them, so a snapshot taken from a list endpoint
+/// (which leaves events out) doesn't make every event look new.
+pub fn diff(previous: Option<&Letter>, current: &Letter) -> Vec<Change> {
+    let Some(previous) = previous else {
+        return vec![Change::NewLetter {
+            letter: current.clone(),
+        }];
+    };
+
+    let mut changes = Vec::new();
+    if previous.status != current.status {
+        changes.push(Change::StatusChanged {
+            letter: current.clone(),
+            from: previous.status.clone(),
+            to: current.status.clone(),
+        });
+    }
+    if let (Some(known), Some(events)) = (&previous.events, &current.events) {
+        for event in events {
+            if !known.iter().any(|known| known.key() == event.key()) {
+                changes.push(Change::NewEvent {
+                    letter: current.clone(),
+                    event: event.clone(),
+                });
+            }
+        }
+    }
+    changes
+}
@@ -1,38 +1,452 @@
-use clap::{Parser, ValueEnum};
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use clap::{ArgGroup, Args, Parser, Subcommand, ValueEnum};
+use confy::ConfyError;
+use orphy::filter::{self, DateField, MailFilter, SortKey};
+use orphy::instance::{DEFAULT_API_PATH, Instance};
+use orphy::mail::parse_timestamp;
+use orphy::{Letter, MailType, Notifier, Webhook};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::time::Duration;
 
-#[derive(ValueEnum, Clone)]
-pub enum MailType {
-    Legacy,
-    Letter,
-    Package,
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Yaml,
+}
+
+/// The formats of `orphy export feed`.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Atom,
+    Rss,
+}
+
+/// Everything `--format` can be set to. It's one flag for every command, so the feed formats are
+/// in here too and [`FormatArg::split`] sorts them out.
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum FormatArg {
+    Table,
+    Json,
+    Ndjson,
+    Csv,
+    Yaml,
+    Atom,
+    Rss,
+}
+
+impl FormatArg {
+    /// The output format, or the feed format for `orphy export feed`.
+    pub fn split(self) -> Result<Format, FeedFormat> {
+        match self {
+            FormatArg::Table => Ok(Format::Table),
+            FormatArg::Json => Ok(Format::Json),
+            FormatArg::Ndjson => Ok(Format::Ndjson),
+            FormatArg::Csv => Ok(Format::Csv),
+            FormatArg::Yaml => Ok(Format::Yaml),
+            FormatArg::Atom => Err(FeedFormat::Atom),
+            FormatArg::Rss => Err(FeedFormat::Rss),
+        }
+    }
 }
 
 #[derive(Parser)]
-pub enum Cli {
+pub struct Cli {
+    /// Output format for mail, view, fetch and stats. atom and rss are for export feed
+    #[arg(short, long, global = true, value_enum, default_value_t = FormatArg::Table)]
+    pub format: FormatArg,
+    /// Which saved profile to use instead of the active one
+    #[arg(short, long, global = true, value_parser = parse_profile_name)]
+    pub profile: Option<String>,
+    #[command(flatten)]
+    pub network: NetworkArgs,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+/// Flags for how requests are made. They win over the config.
+#[derive(Args)]
+pub struct NetworkArgs {
+    /// Base url of the instance to talk to instead of the profile's (or ORPHY_INSTANCE)
+    #[arg(long, global = true, value_parser = parse_instance)]
+    pub instance: Option<String>,
+    /// How long to wait for Hack Club Mail to answer, e.g. 10s [default: 30s]
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub timeout: Option<Duration>,
+    /// How many times to retry a request that failed on a blip [default: 3]
+    #[arg(long, global = true)]
+    pub retries: Option<u32>,
+    /// Send at most this many requests a second
+    #[arg(long, global = true)]
+    pub rate_limit: Option<u32>,
+    /// Don't talk to Hack Club Mail, only show what was cached the last time
+    #[arg(long, global = true)]
+    pub offline: bool,
+    /// Use cached responses up to this old (e.g. 10m) instead of asking Hack Club Mail
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub max_age: Option<Duration>,
+    /// How many pieces of mail to look up at once [default: 8]
+    #[arg(long, global = true, value_parser = clap::value_parser!(u16).range(1..))]
+    pub concurrency: Option<u16>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
     Mail {
         #[arg(short, long)]
         r#type: Option<MailType>,
+        #[command(flatten)]
+        query: MailQuery,
     },
+    /// Show the details and events of one or more pieces of mail
+    #[command(group = ArgGroup::new("ids").required(true).multiple(true))]
     View {
+        /// Id of the mail to show, can be given more than once
+        #[arg(short, long, group = "ids")]
+        id: Vec<String>,
+        /// Show every piece of mail (of --type, if given)
+        #[arg(long, group = "ids", conflicts_with_all = ["id", "stdin"])]
+        all: bool,
+        /// Read more ids from stdin, separated by whitespace
+        #[arg(long, group = "ids")]
+        stdin: bool,
+        /// Kind of mail the ids belong to, guessed from each id when left out
+        #[arg(short, long)]
+        r#type: Option<MailType>,
+        /// Only show the timeline of events
+        #[arg(long, conflicts_with = "latest_event")]
+        events_only: bool,
+        /// Only show the most recent event, one line each
+        #[arg(long)]
+        latest_event: bool,
+    },
+    /// Work out how long your mail takes to arrive
+    Stats {
         #[arg(short, long)]
-        id: String,
+        r#type: Option<MailType>,
     },
-    Fetch,
+    Fetch {
+        /// Add up the mail of every saved profile
+        #[arg(long)]
+        all_profiles: bool,
+    },
+    /// Save the api key of the selected profile
     Setup {
-        api_key: String,
+        /// Leave out when using --token-command
+        #[arg(required_unless_present = "token_command")]
+        api_key: Option<String>,
+        #[command(flatten)]
+        key: KeyOptions,
+    },
+    /// Manage the accounts orphy knows about
+    Profile {
+        #[command(subcommand)]
+        command: ProfileCommand,
     },
+    /// Write your mail to a file other apps can read
+    Export {
+        #[command(subcommand)]
+        command: ExportCommand,
+    },
+    /// Sync your mail into the local history and show what changed since the last sync
+    Changes,
+    /// Keep checking your mail and print a line whenever something changes
+    Watch {
+        /// Only watch this piece of mail
+        #[arg(short, long)]
+        id: Option<String>,
+        /// Kind of mail the id belongs to, guessed from the id when left out
+        #[arg(short, long, requires = "id")]
+        r#type: Option<MailType>,
+        /// How long to wait between checks, e.g. 30s, 5m or 1h
+        #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+        interval: Duration,
+        /// Shell command to run for every change, told about it in ORPHY_* environment variables
+        #[arg(long)]
+        exec: Option<String>,
+        /// File or named pipe to append a line of json to for every change
+        #[arg(long)]
+        append: Option<PathBuf>,
+        /// Ring the terminal bell when something changes
+        #[arg(long)]
+        bell: bool,
+    },
+    /// Share your mail as json and a web dashboard, without handing out your api key
+    Serve {
+        /// Address to listen on, e.g. 0.0.0.0:8080 to let the rest of your network in
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: SocketAddr,
+        /// How long to wait between refreshes, e.g. 30s, 5m or 1h
+        #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+        interval: Duration,
+    },
+    /// Serve numbers about your mail and the requests orphy sends as Prometheus metrics
+    Exporter {
+        /// Address to listen on, e.g. 0.0.0.0:9185 to let Prometheus on another machine in
+        #[arg(long, default_value = "127.0.0.1:9185")]
+        bind: SocketAddr,
+        /// How long to wait between refreshes, e.g. 30s, 5m or 1h
+        #[arg(long, default_value = "5m", value_parser = humantime::parse_duration)]
+        interval: Duration,
+    },
+    /// Browse your mail in a full screen terminal ui
+    Tui,
     Credit,
     Fun,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Subcommand)]
+pub enum ExportCommand {
+    /// An iCalendar file with an event for every milestone of your mail
+    Ics {
+        /// File to write to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short, long)]
+        r#type: Option<MailType>,
+    },
+    /// A feed with an entry for every new piece of mail and tracking event, picked with
+    /// --format atom or --format rss [default: atom]
+    Feed {
+        /// File to write to instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[arg(short, long)]
+        r#type: Option<MailType>,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ProfileCommand {
+    /// Save an api key under a new name, or replace the one already saved under it
+    Add {
+        #[arg(value_parser = parse_profile_name)]
+        name: String,
+        /// Leave out when using --token-command
+        #[arg(required_unless_present = "token_command")]
+        api_key: Option<String>,
+        /// Base url of the instance this account lives on
+        #[arg(long, value_parser = parse_instance)]
+        base: Option<String>,
+        /// Where the api lives on that instance, when it isn't /api/public/v1/
+        #[arg(long)]
+        api_path: Option<String>,
+        #[command(flatten)]
+        key: KeyOptions,
+    },
+    /// List the saved profiles
+    List,
+    /// Use this profile whenever --profile isn't given
+    Use {
+        #[arg(value_parser = parse_profile_name)]
+        name: String,
+    },
+    /// Forget a profile and its api key, and delete its history and cached mail
+    Remove {
+        #[arg(value_parser = parse_profile_name)]
+        name: String,
+        /// Keep the profile's history and cached mail, for a profile added again under this name
+        #[arg(long)]
+        keep_data: bool,
+    },
+}
+
+/// How an api key is kept when it's saved.
+#[derive(Args)]
+pub struct KeyOptions {
+    /// Encrypt the api key with a passphrase (read from ORPHY_PASSPHRASE or asked for)
+    #[arg(long, conflicts_with = "token_command")]
+    pub encrypt: bool,
+    /// Don't save the api key at all, run this command to get it instead
+    #[arg(long, conflicts_with = "api_key")]
+    pub token_command: Option<String>,
+    /// Save the api key without checking it with Hack Club Mail first
+    #[arg(long)]
+    pub no_verify: bool,
+}
+
+/// Options for narrowing down and ordering a list of mail.
+#[derive(Args, Clone, Default)]
+pub struct MailQuery {
+    /// Only show mail with this status
+    #[arg(long)]
+    pub status: Option<String>,
+    /// Only show mail with this tag, can be given more than once
+    #[arg(long)]
+    pub tag: Vec<String>,
+    /// Only show mail from this date or time onwards
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only show mail up to and including this date or time
+    #[arg(long, value_parser = parse_until)]
+    pub until: Option<DateTime<Utc>>,
+    /// Which date --since and --until look at
+    #[arg(long, value_enum, default_value_t = DateField::Created)]
+    pub date: DateField,
+    /// Only show mail with this in its title or tags
+    #[arg(long)]
+    pub search: Option<String>,
+    /// Sort by this instead of the order Hack Club Mail uses
+    #[arg(long, value_enum)]
+    pub sort: Option<SortKey>,
+    /// Reverse the order
+    #[arg(long)]
+    pub reverse: bool,
+    /// Show at most this many
+    #[arg(long)]
+    pub limit: Option<usize>,
+}
+
+impl MailQuery {
+    pub fn apply(&self, letters: Vec<Letter>) -> Vec<Letter> {
+        let filter = MailFilter {
+            status: self.status.clone(),
+            tags: self.tag.clone(),
+            since: self.since,
+            until: self.until,
+            date_field: self.date,
+            search: self.search.clone(),
+        };
+        let mut letters = filter.apply(letters);
+        if let Some(key) = self.sort {
+            filter::sort(&mut letters, key);
+        }
+        if self.reverse {
+            letters.reverse();
+        }
+        if let Some(limit) = self.limit {
+            letters.truncate(limit);
+        }
+        letters
+    }
+}
+
+fn parse_since(date: &str) -> Result<DateTime<Utc>, String> {
+    parse_timestamp(date).ok_or_else(|| format!("`{date}` isn't a date like 2025-06-01"))
+}
+
+// a plain date means the whole day
+fn parse_until(date: &str) -> Result<DateTime<Utc>, String> {
+    if let Ok(day) = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d") {
+        let next_day = day
+            .checked_add_days(Days::new(1))
+            .ok_or("date is too far away")?;
+        return Ok(
+            next_day.and_hms_opt(0, 0, 0).unwrap().and_utc() - chrono::Duration::nanoseconds(1)
+        );
+    }
+    parse_since(date)
+}
+
+/// The profile used when neither `--profile` nor `orphy profile use` picked one.
+pub const DEFAULT_PROFILE: &str = "default";
+
+const APP_NAME: &str = "orphy_hackclub_mail_client";
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct Config {
+    /// Where the api key was kept before there were profiles. It's moved into the default
+    /// profile when the config is loaded.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub api_key: String,
+    /// The profile picked with `orphy profile use`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub active: Option<String>,
+    pub profiles: BTreeMap<String, Profile>,
+    /// How long to wait for Hack Club Mail to answer, e.g. "10s"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// How many times to retry a request that failed on a blip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+    /// At most this many requests a second
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<u32>,
+    /// What `orphy watch` does when mail changes, on top of printing it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notify: Vec<Notifier>,
+    /// Where `orphy changes` and `orphy watch` POST every change they find
+    #[serde(rename = "webhook", skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<Webhook>,
+}
+
+/// One Hack Club Mail account.
+#[derive(Serialize, Deserialize, Default, Clone)]
+#[serde(default)]
+pub struct Profile {
+    /// The api key in plain text, when it isn't kept one of the other ways
+    #[serde(skip_serializing_if = "String::is_empty")]
     pub api_key: String,
+    /// The api key encrypted with a passphrase
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_key: Option<String>,
+    /// A command that prints the api key, e.g. a password manager
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token_command: Option<String>,
+    /// The instance this account lives on, when it isn't the usual one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Where the api lives on the instance, when it isn't the usual place
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_path: Option<String>,
+}
+
+impl Config {
+    pub fn load() -> Result<Self, ConfyError> {
+        let mut config: Self = confy::load(APP_NAME, None)?;
+        if !config.api_key.is_empty() {
+            let api_key = std::mem::take(&mut config.api_key);
+            config
+                .profiles
+                .entry(String::from(DEFAULT_PROFILE))
+                .or_insert(Profile {
+                    api_key,
+                    ..Profile::default()
+                });
+        }
+        Ok(config)
+    }
+
+    pub fn save(&self) -> Result<(), ConfyError> {
+        confy::store(APP_NAME, None, self)
+    }
+
+    pub fn path() -> Result<PathBuf, ConfyError> {
+        confy::get_configuration_file_path(APP_NAME, None)
+    }
+
+    /// The name of the profile to use: `selected` if it was given, then the active one.
+    pub fn profile_name(&self, selected: Option<&str>) -> String {
+        selected
+            .or(self.active.as_deref())
+            .unwrap_or(DEFAULT_PROFILE)
+            .to_string()
+    }
+}
+
+fn parse_instance(base: &str) -> Result<String, String> {
+    Instance::new(base, DEFAULT_API_PATH)
+        .map(|instance| instance.base().to_string())
+        .map_err(|err| err.to_string())
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self { api_key: "".into() }
+// profile names end up in file names, so keep them boring
+fn parse_profile_name(name: &str) -> Result<String, String> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    {
+        Ok(name.to_string())
+    } else {
+        Err(String::from(
+            "profile names can only have letters, numbers, - and _",
+        ))
     }
 }
@@ -0,0 +1,145 @@
+use crate::mail::RecordError;
+use reqwest::StatusCode;
+use thiserror::Error;
+
+/// Everything that can go wrong while talking to Hack Club Mail.
+///
+/// Each variant has its own process exit code (see [`MailError::exit_code`]) so scripts can tell
+/// a rejected token apart from the server being down.
+#[derive(Debug, Error)]
+pub enum MailError {
+    #[error(
+        "Your api key was rejected (401). It might be invalid or expired, run orphy setup [api key] with a new one."
+    )]
+    Unauthorized,
+    #[error("Your api key isn't allowed to see this (403).")]
+    Forbidden,
+    #[error("That mail doesn't exist (404).")]
+    NotFound,
+    #[error("Hack Club Mail is rate limiting you (429). Wait a bit and try again.")]
+    RateLimited,
+    #[error("Hack Club Mail is having trouble ({0}). Try again later.")]
+    Server(StatusCode),
+    #[error("Hack Club Mail answered with an unexpected status ({0}).")]
+    Status(StatusCode),
+    #[error("Couldn't reach Hack Club Mail: {0}")]
+    Network(#[from] reqwest::Error),
+    #[error("Hack Club Mail didn't answer with valid json: {0}")]
+    Decode(#[from] serde_json::Error),
+    #[error("Hack Club Mail's response is missing `{0}`. The api might have changed.")]
+    Schema(String),
+    #[error("Hack Club Mail sent a {0}")]
+    Record(#[from] RecordError),
+    #[error("The instance url {0}.")]
+    InvalidUrl(String),
+    #[error("There's no cached copy of this yet. Run it once while online first.")]
+    NotCached,
+    #[error("You don't have an api key! Run orphy setup [your api key] with your api key.")]
+    NoApiKey,
+}
+
+impl MailError {
+    /// Maps a non-success status code onto the matching variant.
+    pub fn from_status(status: StatusCode) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED => MailError::Unauthorized,
+            StatusCode::FORBIDDEN => MailError::Forbidden,
+            StatusCode::NOT_FOUND => MailError::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => MailError::RateLimited,
+            status if status.is_server_error() => MailError::Server(status),
+            status => MailError::Status(status),
+        }
+    }
+
+    /// Whether trying again later might work, as opposed to problems like a rejected api key
+    /// that won't go away on their own.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            MailError::RateLimited
+                | MailError::Server(_)
+                | MailError::Network(_)
+                | MailError::Decode(_)
+        )
+    }
+
+    /// The exit code orphy uses when a command fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            MailError::Unauthorized => 10,
+            MailError::Forbidden => 11,
+            MailError::NotFound => 12,
+            MailError::RateLimited => 13,
+            MailError::Server(_) => 14,
+            MailError::Status(_) => 15,
+            MailError::Network(_) => 16,
+            MailError::Decode(_) => 17,
+            MailError::Schema(_) => 18,
+            MailError::Record(_) => 19,
+            MailError::InvalidUrl(_) => 20,
+            MailError::NotCached => 21,
+            MailError::NoApiKey => 22,
+        }
+    }
+
+    /// A short name for this kind of error, e.g. `rate_limited`, for labelling metrics.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MailError::Unauthorized => "unauthorized",
+            MailError::Forbidden => "forbidden",
+            MailError::NotFound => "not_found",
+            MailError::RateLimited => "rate_limited",
+            MailError::Server(_) => "server",
+            MailError::Status(_) => "status",
+            MailError::Network(_) => "network",
+            MailError::Decode(_) => "decode",
+            MailError::Schema(_) => "schema",
+            MailError::Record(_) => "record",
+            MailError::InvalidUrl(_) => "invalid_url",
+            MailError::NotCached => "not_cached",
+            MailError::NoApiKey => "no_api_key",
+        }
+    }
+}
+
+/// Failures reading or writing the local mail history.
+#[derive(Debug, Error)]
+pub enum StoreError {
+    #[error("Couldn't read or write the mail history: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+    #[error("The mail history has a record orphy can't read: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Couldn't create the mail history: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Failures running one of the actions of a [`Notifier`](crate::notify::Notifier).
+#[derive(Debug, Error)]
+pub enum NotifyError {
+    #[error("Couldn't run the notify command `{command}`: {source}")]
+    Spawn {
+        command: String,
+        source: std::io::Error,
+    },
+    #[error("The notify command `{command}` failed ({status})")]
+    Command {
+        command: String,
+        status: std::process::ExitStatus,
+    },
+    #[error("Couldn't write a notification to {}: {source}", path.display())]
+    Write {
+        path: std::path::PathBuf,
+        source: std::io::Error,
+    },
+    #[error("Couldn't write a notification to {}: no reader on the pipe", path.display())]
+    NoReader { path: std::path::PathBuf },
+}
+
+/// Failures delivering a change to a [`Webhook`](crate::webhook::Webhook), after any retries.
+#[derive(Debug, Error)]
+pub enum WebhookError {
+    #[error("Couldn't reach the webhook {url}: {source}")]
+    Network { url: String, source: reqwest::Error },
+    #[error("The webhook {url} answered with {status}")]
+    Status { url: String, status: StatusCode },
+}
@@ -0,0 +1,143 @@
+use crate::mail::{Event, Letter, compact_timestamp};
+use chrono::{DateTime, Utc};
+
+/// One thing that happened to a piece of mail: it showed up, or it got a new tracking event.
+#[derive(Clone, Debug)]
+pub struct Entry<'a> {
+    /// Stays the same every time the feed is made, built from the id of the mail and, for
+    /// events, their time.
+    pub guid: String,
+    pub title: String,
+    pub link: Option<&'a str>,
+    pub published: DateTime<Utc>,
+    pub summary: String,
+}
+
+/// The entries for a list of mail, newest first. Mail and events without a time are left out
+/// since there's no telling where they go.
+pub fn entries(letters: &[Letter]) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    for letter in letters {
+        let Some(id) = &letter.id else {
+            continue;
+        };
+        let title = letter.title.as_deref().unwrap_or(id);
+        let link = letter
+            .public_url
+            .as_deref()
+            .or(letter.tracking_link.as_deref());
+
+        if let Some(created_at) = letter.created_at {
+            entries.push(Entry {
+                guid: format!("urn:orphy:{id}"),
+                title: format!(
+                    "New {}: {title}",
+                    letter.letter_type.as_deref().unwrap_or("mail")
+                ),
+                link,
+                published: created_at,
+                summary: summary(letter, None),
+            });
+        }
+        for (happened_at, count, event) in letter.dated_events() {
+            entries.push(Entry {
+                guid: format!("urn:orphy:{id}:{}:{count}", compact_timestamp(happened_at)),
+                title: format!(
+                    "{title}: {}",
+                    event.description.as_deref().unwrap_or("new event")
+                ),
+                link,
+                published: happened_at,
+                summary: summary(letter, Some(event)),
+            });
+        }
+    }
+    entries.sort_by(|a, b| b.published.cmp(&a.published).then(a.guid.cmp(&b.guid)));
+    entries
+}
+
+/// An Atom feed of the mail. `link` is the instance it came from and `now` is used as the time
+/// the feed was updated when there aren't any entries.
+pub fn atom(letters: &[Letter], link: &str, now: DateTime<Utc>) -> String {
+    let entries = entries(letters);
+    let updated = entries.first().map_or(now, |entry| entry.published);
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed += "<feed xmlns=\"http://www.w3.org/2005/Atom\">\n";
+    feed += "  <title>Hack Club Mail</title>\n";
+    feed += "  <id>urn:orphy:feed</id>\n";
+    feed += &format!("  <link href=\"{}\"/>\n", escape(link));
+    feed += &format!("  <updated>{}</updated>\n", updated.to_rfc3339());
+    feed += "  <author><name>Hack Club Mail</name></author>\n";
+    feed += "  <generator>orphy</generator>\n";
+    for entry in entries {
+        feed += "  <entry>\n";
+        feed += &format!("    <id>{}</id>\n", escape(&entry.guid));
+        feed += &format!("    <title>{}</title>\n", escape(&entry.title));
+        if let Some(link) = entry.link {
+            feed += &format!("    <link href=\"{}\"/>\n", escape(link));
+        }
+        feed += &format!(
+            "    <published>{}</published>\n",
+            entry.published.to_rfc3339()
+        );
+        feed += &format!("    <updated>{}</updated>\n", entry.published.to_rfc3339());
+        feed += &format!("    <summary>{}</summary>\n", escape(&entry.summary));
+        feed += "  </entry>\n";
+    }
+    feed += "</feed>\n";
+    feed
+}
+
+/// An RSS 2.0 feed of the mail, see [`atom`].
+pub fn rss(letters: &[Letter], link: &str, now: DateTime<Utc>) -> String {
+    let entries = entries(letters);
+    let updated = entries.first().map_or(now, |entry| entry.published);
+
+    let mut feed = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    feed += "<rss version=\"2.0\">\n";
+    feed += "  <channel>\n";
+    feed += "    <title>Hack Club Mail</title>\n";
+    feed += &format!("    <link>{}</link>\n", escape(link));
+    feed += "    <description>New mail and tracking events from Hack Club Mail</description>\n";
+    feed += &format!(
+        "    <lastBuildDate>{}</lastBuildDate>\n",
+        updated.to_rfc2822()
+    );
+    feed += "    <generator>orphy</generator>\n";
+    for entry in entries {
+        feed += "    <item>\n";
+        feed += &format!(
+            "      <guid isPermaLink=\"false\">{}</guid>\n",
+            escape(&entry.guid)
+        );
+        feed += &format!("      <title>{}</title>\n", escape(&entry.title));
+        if let Some(link) = entry.link {
+            feed += &format!("      <link>{}</link>\n", escape(link));
+        }
+        feed += &format!(
+            "      <pubDate>{}</pubDate>\n",
+            entry.published.to_rfc2822()
+        );
+        feed += &format!(
+            "      <description>{}</description>\n",
+            escape(&entry.summary)
+        );
+        feed += "    </item>\n";
+    }
+    feed += "  </channel>\n";
+    feed += "</rss>\n";
+    feed
+}
+
+fn summary(letter: &Letter, event: Option<&Event>) -> String {
+    letter.describe(event).join("\n")
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
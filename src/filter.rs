@@ -0,0 +1,124 @@
+use crate::mail::Letter;
+use chrono::{DateTime, Utc};
+use std::cmp::Ordering;
+
+/// Which timestamp `since` and `until` are compared against.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum DateField {
+    #[default]
+    Created,
+    Updated,
+}
+
+/// What mail can be sorted by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum SortKey {
+    Created,
+    Updated,
+    Status,
+    Title,
+}
+
+/// Narrows a list of mail down. Every condition that's set has to match, and text is compared
+/// without caring about case.
+#[derive(Clone, Debug, Default)]
+pub struct MailFilter {
+    /// Only mail with exactly this status.
+    pub status: Option<String>,
+    /// Only mail with all of these tags.
+    pub tags: Vec<String>,
+    /// Only mail at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Only mail at or before this time.
+    pub until: Option<DateTime<Utc>>,
+    pub date_field: DateField,
+    /// Only mail with this in its title or one of its tags.
+    pub search: Option<String>,
+}
+
+impl MailFilter {
+    pub fn matches(&self, letter: &Letter) -> bool {
+        if let Some(status) = &self.status
+            && !letter
+                .status
+                .as_deref()
+                .is_some_and(|current| current.eq_ignore_ascii_case(status))
+        {
+            return false;
+        }
+
+        let tags = letter.tags.as_deref().unwrap_or_default();
+        if !self
+            .tags
+            .iter()
+            .all(|wanted| tags.iter().any(|tag| tag.eq_ignore_ascii_case(wanted)))
+        {
+            return false;
+        }
+
+        if self.since.is_some() || self.until.is_some() {
+            let date = match self.date_field {
+                DateField::Created => letter.created_at,
+                DateField::Updated => letter.updated_at,
+            };
+            let Some(date) = date else {
+                return false;
+            };
+            if self.since.is_some_and(|since| date < since)
+                || self.until.is_some_and(|until| date > until)
+            {
+                return false;
+            }
+        }
+
+        if let Some(search) = &self.search {
+            let search = search.to_lowercase();
+            let in_title = letter
+                .title
+                .as_deref()
+                .is_some_and(|title| title.to_lowercase().contains(&search));
+            let in_tags = tags.iter().any(|tag| tag.to_lowercase().contains(&search));
+            if !in_title && !in_tags {
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Keeps only the mail that matches.
+    pub fn apply(&self, letters: Vec<Letter>) -> Vec<Letter> {
+        letters
+            .into_iter()
+            .filter(|letter| self.matches(letter))
+            .collect()
+    }
+}
+
+/// Sorts mail oldest first for dates and alphabetically otherwise. Mail missing the field goes
+/// last.
+pub fn sort(letters: &mut [Letter], key: SortKey) {
+    fn missing_last<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    letters.sort_by(|a, b| match key {
+        SortKey::Created => missing_last(a.created_at, b.created_at),
+        SortKey::Updated => missing_last(a.updated_at, b.updated_at),
+        SortKey::Status => missing_last(
+            a.status.as_ref().map(|status| status.to_lowercase()),
+            b.status.as_ref().map(|status| status.to_lowercase()),
+        ),
+        SortKey::Title => missing_last(
+            a.title.as_ref().map(|title| title.to_lowercase()),
+            b.title.as_ref().map(|title| title.to_lowercase()),
+        ),
+    });
+}
@@ -0,0 +1,125 @@
+use crate::mail::{Event, Letter, compact_timestamp};
+use chrono::{DateTime, Utc};
+
+/// Builds an iCalendar file with a VEVENT for every milestone of the mail: when it was created,
+/// every tracking event with a time and when it was delivered. `stamp` is when the calendar was
+/// made.
+///
+/// UIDs only depend on the mail, so a calendar app subscribed to the file updates events in place
+/// instead of duplicating them when it's made again.
+pub fn calendar(letters: &[Letter], stamp: DateTime<Utc>) -> String {
+    let mut calendar = Calendar::default();
+    calendar.line("BEGIN:VCALENDAR");
+    calendar.line("VERSION:2.0");
+    calendar.line("PRODID:-//orphy//Hack Club Mail//EN");
+    calendar.line("CALSCALE:GREGORIAN");
+    calendar.line("X-WR-CALNAME:Hack Club Mail");
+
+    for letter in letters {
+        let Some(id) = &letter.id else {
+            continue;
+        };
+        let title = letter.title.as_deref().unwrap_or(id);
+
+        if let Some(created_at) = letter.created_at {
+            calendar.event(VEvent {
+                uid: format!("{id}-created"),
+                stamp,
+                start: created_at,
+                summary: format!("{title} created"),
+                letter,
+                event: None,
+            });
+        }
+
+        for (happened_at, count, event) in letter.dated_events() {
+            calendar.event(VEvent {
+                uid: format!("{id}-{}-{count}", compact_timestamp(happened_at)),
+                stamp,
+                start: happened_at,
+                summary: format!(
+                    "{title}: {}",
+                    event.description.as_deref().unwrap_or("new event")
+                ),
+                letter,
+                event: Some(event),
+            });
+        }
+
+        // delivery scans are already on the calendar, this covers mail that's only marked delivered
+        if !letter.events.iter().flatten().any(Event::is_delivery)
+            && let Some(delivered_at) = letter.delivered_at()
+        {
+            calendar.event(VEvent {
+                uid: format!("{id}-delivered"),
+                stamp,
+                start: delivered_at,
+                summary: format!("{title} delivered"),
+                letter,
+                event: None,
+            });
+        }
+    }
+
+    calendar.line("END:VCALENDAR");
+    calendar.text
+}
+
+struct VEvent<'a> {
+    uid: String,
+    stamp: DateTime<Utc>,
+    start: DateTime<Utc>,
+    summary: String,
+    letter: &'a Letter,
+    event: Option<&'a Event>,
+}
+
+#[derive(Default)]
+struct Calendar {
+    text: String,
+}
+
+impl Calendar {
+    fn event(&mut self, vevent: VEvent) {
+        let letter = vevent.letter;
+        let mut description = letter.describe(vevent.event);
+        description.extend(letter.public_url.clone());
+        description.extend(letter.tracking_link.clone());
+
+        self.line("BEGIN:VEVENT");
+        self.line(&format!("UID:{}@orphy", escape(&vevent.uid)));
+        self.line(&format!("DTSTAMP:{}", compact_timestamp(vevent.stamp)));
+        self.line(&format!("DTSTART:{}", compact_timestamp(vevent.start)));
+        self.line(&format!("SUMMARY:{}", escape(&vevent.summary)));
+        if !description.is_empty() {
+            self.line(&format!("DESCRIPTION:{}", escape(&description.join("\n"))));
+        }
+        if let Some(url) = letter.public_url.as_ref().or(letter.tracking_link.as_ref()) {
+            self.line(&format!("URL:{url}"));
+        }
+        self.line("END:VEVENT");
+    }
+
+    /// Adds a content line, folded so no line is longer than 75 bytes.
+    fn line(&mut self, line: &str) {
+        let mut width = 0;
+        for c in line.chars() {
+            if width + c.len_utf8() > 75 {
+                self.text.push_str("\r\n ");
+                width = 1;
+            }
+            self.text.push(c);
+            width += c.len_utf8();
+        }
+        self.text.push_str("\r\n");
+    }
+}
+
+/// Escapes a TEXT value.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
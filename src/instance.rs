@@ -0,0 +1,91 @@
+use crate::error::MailError;
+use url::{ParseError, Url};
+
+/// The instance everyone uses.
+pub const DEFAULT_BASE: &str = "https://mail.hackclub.com";
+/// Where the public api lives on an instance.
+pub const DEFAULT_API_PATH: &str = "/api/public/v1/";
+
+/// Where a Hack Club Mail api lives: the base url of the instance and the path of the api on it.
+///
+/// Urls are built segment by segment, so slashes never double up and an id can't reach outside
+/// the endpoint it's meant for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instance {
+    base: Url,
+    api_path: String,
+}
+
+impl Instance {
+    /// Checks that `base` is an http(s) url without a query or fragment. It may have a path of its
+    /// own, for instances that don't live at the root of their domain.
+    pub fn new(base: &str, api_path: &str) -> Result<Self, MailError> {
+        let invalid = |reason: &str| MailError::InvalidUrl(format!("`{base}` {reason}"));
+        let url = Url::parse(base.trim()).map_err(|err| match err {
+            ParseError::RelativeUrlWithoutBase => invalid("has to start with http:// or https://"),
+            err => invalid(&format!("isn't a url ({err})")),
+        })?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid("has to start with http:// or https://"));
+        }
+        if url.host_str().is_none_or(str::is_empty) {
+            return Err(invalid("doesn't have a host"));
+        }
+        if url.query().is_some() || url.fragment().is_some() {
+            return Err(invalid("can't have a query or fragment"));
+        }
+
+        let api_path = segments(api_path).collect::<Vec<_>>().join("/");
+        Ok(Self {
+            base: url,
+            api_path: if api_path.is_empty() {
+                String::from("/")
+            } else {
+                format!("/{api_path}/")
+            },
+        })
+    }
+
+    /// The base url without a trailing slash, e.g. `https://mail.hackclub.com`.
+    pub fn base(&self) -> &str {
+        self.base.as_str().trim_end_matches('/')
+    }
+
+    /// The path of the api, e.g. `/api/public/v1/`.
+    pub fn api_path(&self) -> &str {
+        &self.api_path
+    }
+
+    /// The url of an endpoint of the api, e.g. `["letters", id]`. Each segment is escaped as a
+    /// whole, slashes included.
+    pub fn api_url(&self, endpoint: &[&str]) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("instance urls always have a path")
+            .pop_if_empty()
+            .extend(segments(&self.api_path))
+            .extend(endpoint);
+        url
+    }
+
+    /// The url of a path the api handed out, like the `path` of a letter. It's taken to be
+    /// relative to the base url.
+    pub fn url(&self, path: &str) -> Url {
+        let mut url = self.base.clone();
+        url.path_segments_mut()
+            .expect("instance urls always have a path")
+            .pop_if_empty()
+            .extend(segments(path));
+        url
+    }
+}
+
+impl Default for Instance {
+    fn default() -> Self {
+        Self::new(DEFAULT_BASE, DEFAULT_API_PATH).expect("the default instance is valid")
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
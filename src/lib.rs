@@ -0,0 +1,49 @@
+//! A client for [Hack Club Mail](https://mail.hackclub.com).
+//!
+//! ```no_run
+//! use orphy::{MailClient, MailType};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), orphy::MailError> {
+//! let client = MailClient::builder("your api key")
+//!     .timeout(Duration::from_secs(10))
+//!     .build()?;
+//!
+//! for letter in client.get_mail(Some(MailType::Package)).await?.letters {
+//!     println!("{:?}: {:?}", letter.id, letter.status);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod cache;
+pub mod carrier;
+pub mod changes;
+pub mod error;
+pub mod feed;
+pub mod filter;
+pub mod ics;
+pub mod instance;
+pub mod mail;
+pub mod metrics;
+pub mod notify;
+pub mod retry;
+pub mod stats;
+pub mod store;
+pub mod watch;
+pub mod webhook;
+
+pub use cache::Cache;
+pub use carrier::Carrier;
+pub use changes::Change;
+pub use error::{MailError, NotifyError, StoreError, WebhookError};
+pub use filter::MailFilter;
+pub use instance::Instance;
+pub use mail::{Event, Letter, MailClient, MailClientBuilder, MailList, MailType, RecordError};
+pub use metrics::RequestMetrics;
+pub use notify::Notifier;
+pub use retry::Retry;
+pub use stats::Stats;
+pub use store::Store;
+pub use watch::Watcher;
+pub use webhook::Webhook;
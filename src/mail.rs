@@ -1,43 +1,368 @@
-use crate::cli::MailType;
-use anyhow::Error;
+use crate::cache::{Cache, CachedResponse};
+use crate::carrier::Carrier;
+use crate::error::MailError;
+use crate::instance::{DEFAULT_API_PATH, DEFAULT_BASE, Instance};
+use crate::metrics::RequestMetrics;
+use crate::retry::{RateLimiter, Retry, parse_retry_after};
 use chrono::prelude::*;
-use reqwest::Client;
-use serde_json::Value;
+use futures::{StreamExt, stream};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Url};
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
+/// Client for the Hack Club Mail public api.
+///
+/// Use [`MailClient::new`] for the defaults or [`MailClient::builder`] to point it somewhere else.
 pub struct MailClient {
     auth_token: String,
-    pub base: String,
-    pub api_path: String,
+    instance: Instance,
+    timeout: Option<Duration>,
+    retry: Retry,
+    limiter: RateLimiter,
+    cache: Option<Cache>,
+    offline: bool,
+    max_age: Option<Duration>,
+    stale_fallback: bool,
+    cached_at: Mutex<Option<DateTime<Utc>>>,
+    requests: Mutex<RequestMetrics>,
+    concurrency: usize,
     client: Client,
 }
 
+/// How long a request gets before it's given up on, unless the builder says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// How many requests batch lookups have going at once, unless the builder says otherwise.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
+/// Builds a [`MailClient`] with a custom instance, timeout, retries, rate limit or reqwest client.
+pub struct MailClientBuilder {
+    client: MailClient,
+    base: Option<String>,
+    api_path: Option<String>,
+}
+
+/// The kinds of mail Hack Club Mail knows about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "cli", derive(clap::ValueEnum))]
+pub enum MailType {
+    Legacy,
+    Letter,
+    Package,
+}
+
+impl MailType {
+    /// Guesses the kind of mail from an id. Letters and packages have a prefix, legacy records
+    /// are written as `type/id`.
+    pub fn from_id(id: &str) -> Option<Self> {
+        if id.starts_with("ltr!") {
+            Some(MailType::Letter)
+        } else if id.starts_with("pkg!") {
+            Some(MailType::Package)
+        } else if id.contains('/') {
+            Some(MailType::Legacy)
+        } else {
+            None
+        }
+    }
+
+    /// Whether a piece of mail is of this kind, going by its `type`. Anything that isn't a letter
+    /// or a package is legacy.
+    pub fn matches(self, letter: &Letter) -> bool {
+        match self {
+            MailType::Letter => letter.letter_type.as_deref() == Some("letter"),
+            MailType::Package => letter.letter_type.as_deref() == Some("package"),
+            MailType::Legacy => !matches!(
+                letter.letter_type.as_deref(),
+                Some("letter") | Some("package")
+            ),
+        }
+    }
+}
+
 // All of these are options cus letters don't follow a strict schema and sometimes are missing half
 // the details
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Letter {
     pub id: Option<String>,
     pub title: Option<String>,
+    #[serde(rename = "type")]
     pub letter_type: Option<String>,
+    #[serde(rename = "subtype")]
     pub letter_subtype: Option<String>,
     pub tracking_number: Option<String>,
     pub tracking_link: Option<String>,
     pub public_url: Option<String>,
     pub status: Option<String>,
+    #[serde(deserialize_with = "lenient_tags")]
     pub tags: Option<Vec<String>>,
+    #[serde(deserialize_with = "lenient_timestamp")]
     pub created_at: Option<DateTime<Utc>>,
+    #[serde(deserialize_with = "lenient_timestamp")]
     pub updated_at: Option<DateTime<Utc>>,
     pub events: Option<Vec<Event>>,
     pub path: Option<String>,
+    // anything the api sends that we don't know about yet ends up here instead of being dropped
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
 }
 
 // These don't seem to follow as much of an optional schema but i'm putting it here just in case
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Event {
+    #[serde(deserialize_with = "lenient_timestamp")]
     pub happened_at: Option<DateTime<Utc>>,
     pub source: Option<String>,
     pub facility: Option<String>,
     pub description: Option<String>,
     pub location: Option<String>,
+    #[serde(flatten, skip_serializing_if = "Map::is_empty")]
+    pub extra: Map<String, Value>,
+}
+
+/// Letters from a list endpoint. Records that couldn't be decoded are kept in `errors` instead of
+/// failing the whole list.
+#[derive(Default, Debug)]
+pub struct MailList {
+    pub letters: Vec<Letter>,
+    pub errors: Vec<RecordError>,
+}
+
+/// A single record from the api that didn't match the `Letter` schema.
+#[derive(Debug)]
+pub struct RecordError {
+    pub index: usize,
+    pub id: Option<String>,
+    pub source: serde_json::Error,
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.id {
+            Some(id) => write!(
+                f,
+                "record #{} ({id}) is malformed: {}",
+                self.index, self.source
+            ),
+            None => write!(f, "record #{} is malformed: {}", self.index, self.source),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl Letter {
+    /// Decodes a single record, tagging any failure with its position in the list.
+    pub fn from_value(index: usize, value: &Value) -> Result<Self, RecordError> {
+        Letter::deserialize(value).map_err(|source| RecordError {
+            index,
+            id: value.get("id").and_then(Value::as_str).map(String::from),
+            source,
+        })
+    }
+
+    /// The events oldest first. Events without a time keep the order the api sent them in and
+    /// come last.
+    pub fn timeline(&self) -> Vec<&Event> {
+        let mut events: Vec<&Event> = self.events.iter().flatten().collect();
+        events.sort_by_key(|event| (event.happened_at.is_none(), event.happened_at));
+        events
+    }
+
+    /// The events with a time, oldest first, each with a number that tells apart the ones that
+    /// happened at the same time. Together with the id of the mail that's enough to identify an
+    /// event across fetches, as long as the api doesn't go back and change it.
+    pub fn dated_events(&self) -> Vec<(DateTime<Utc>, usize, &Event)> {
+        let mut seen: HashMap<DateTime<Utc>, usize> = HashMap::new();
+        self.timeline()
+            .into_iter()
+            .filter_map(|event| {
+                let happened_at = event.happened_at?;
+                let count = seen.entry(happened_at).or_default();
+                *count += 1;
+                Some((happened_at, *count, event))
+            })
+            .collect()
+    }
+
+    /// A few lines about the mail for feeds and calendars: its status, where `event` happened if
+    /// there is one, and its tracking number.
+    pub fn describe(&self, event: Option<&Event>) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let Some(status) = &self.status {
+            lines.push(format!("Status: {status}"));
+        }
+        if let Some(place) = event.and_then(|event| event.place(", ")) {
+            lines.push(place);
+        }
+        if let Some(tracking_number) = &self.tracking_number {
+            lines.push(format!("Tracking number: {tracking_number}"));
+        }
+        lines
+    }
+
+    /// The most recent event with a time, or the first one the api sent if none of them have
+    /// one.
+    pub fn latest_event(&self) -> Option<&Event> {
+        let events = self.events.as_deref()?;
+        events
+            .iter()
+            .filter(|event| event.happened_at.is_some())
+            .max_by_key(|event| event.happened_at)
+            .or(events.first())
+    }
+
+    /// Whether the mail has arrived, going by its status or a delivery scan.
+    pub fn is_delivered(&self) -> bool {
+        self.status
+            .as_deref()
+            .is_some_and(|status| status.eq_ignore_ascii_case("delivered"))
+            || self.events.iter().flatten().any(Event::is_delivery)
+    }
+
+    /// When the mail arrived: the first delivery scan, or the latest event of mail whose status
+    /// says it was delivered.
+    pub fn delivered_at(&self) -> Option<DateTime<Utc>> {
+        self.timeline()
+            .into_iter()
+            .filter(|event| event.is_delivery())
+            .find_map(|event| event.happened_at)
+            .or_else(|| {
+                self.is_delivered()
+                    .then(|| self.latest_event()?.happened_at)
+                    .flatten()
+            })
+    }
+
+    /// Who's carrying the mail, from the format of its tracking number or else the site its
+    /// tracking link points to.
+    pub fn carrier(&self) -> Option<Carrier> {
+        self.tracking_number
+            .as_deref()
+            .and_then(Carrier::detect)
+            .or_else(|| self.tracking_link.as_deref().and_then(Carrier::from_link))
+    }
+
+    /// Where to track the mail: the tracking link from the api, or the carrier's page for the
+    /// tracking number when there isn't one.
+    pub fn tracking_url(&self) -> Option<String> {
+        if let Some(tracking_link) = &self.tracking_link {
+            return Some(tracking_link.clone());
+        }
+        let tracking_number = self.tracking_number.as_deref()?;
+        Some(Carrier::detect(tracking_number)?.tracking_url(tracking_number))
+    }
+}
+
+impl Event {
+    /// Identifies an event across fetches. Events don't have ids, so this is built from
+    /// everything the api tells us about them.
+    pub fn key(&self) -> String {
+        format!(
+            "{}|{}|{}|{}|{}",
+            self.happened_at
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default(),
+            self.source.as_deref().unwrap_or_default(),
+            self.facility.as_deref().unwrap_or_default(),
+            self.description.as_deref().unwrap_or_default(),
+            self.location.as_deref().unwrap_or_default(),
+        )
+    }
+
+    /// Where the event happened: the facility and the location, whichever are known, joined with
+    /// `separator`.
+    pub fn place(&self, separator: &str) -> Option<String> {
+        let place: Vec<&str> = [self.facility.as_deref(), self.location.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        (!place.is_empty()).then(|| place.join(separator))
+    }
+
+    /// Whether this is the scan of the mail being delivered.
+    pub fn is_delivery(&self) -> bool {
+        let Some(description) = &self.description else {
+            return false;
+        };
+        let description = description.to_lowercase();
+        description.contains("delivered")
+            && !description.contains("undelivered")
+            && !description.contains("not delivered")
+    }
+}
+
+/// A time in the compact UTC format iCalendar uses, e.g. `20250601T160000Z`. It has nothing
+/// that needs escaping, so it's also used in ids.
+pub fn compact_timestamp(date: DateTime<Utc>) -> String {
+    date.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Parses the timestamp formats the api has been seen to send. Anything else is treated as
+/// missing rather than failing the record.
+pub fn parse_timestamp(str: &str) -> Option<DateTime<Utc>> {
+    let str = str.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(str) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = DateTime::parse_from_str(str, "%Y-%m-%d %H:%M:%S%.f %z") {
+        return Some(date.with_timezone(&Utc));
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S UTC",
+    ] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(str, format) {
+            return Some(date.and_utc());
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(str, "%Y-%m-%d") {
+        return date.and_hms_opt(0, 0, 0).map(|date| date.and_utc());
+    }
+    None
+}
+
+fn lenient_timestamp<'de, D>(deserializer: D) -> Result<Option<DateTime<Utc>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(str) => parse_timestamp(&str),
+        Value::Number(num) => num
+            .as_i64()
+            .and_then(|secs| DateTime::from_timestamp(secs, 0)),
+        _ => None,
+    })
+}
+
+// tags sometimes come back as null or with non-string entries mixed in
+fn lenient_tags<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::Array(tags) => Some(
+            tags.into_iter()
+                .filter_map(|tag| match tag {
+                    Value::String(str) => Some(str),
+                    Value::Null => None,
+                    other => Some(other.to_string()),
+                })
+                .collect(),
+        ),
+        _ => None,
+    })
 }
 
 // /api/public/v1/me
@@ -50,6 +375,7 @@ pub struct Event {
 // /api/public/v1/lsv/:type/:id
 
 impl MailClient {
+    /// A client for mail.hackclub.com using `auth_token`.
     pub fn new(auth_token: String) -> Self {
         Self {
             auth_token,
@@ -57,270 +383,385 @@ impl MailClient {
         }
     }
 
-    fn event_from_data(&self, event: &Value) -> Event {
-        let happened_at: Option<DateTime<Utc>> = if let Value::String(str) = &event["happened_at"] {
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
+    /// Starts building a client for `auth_token` with the same defaults as [`MailClient::new`].
+    pub fn builder(auth_token: impl Into<String>) -> MailClientBuilder {
+        MailClientBuilder {
+            client: Self::new(auth_token.into()),
+            base: None,
+            api_path: None,
+        }
+    }
 
-        let description = if let Value::String(str) = &event["description"] {
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
+    /// The instance requests are sent to, e.g. `https://mail.hackclub.com`.
+    pub fn base(&self) -> &str {
+        self.instance.base()
+    }
 
-        let location = if let Value::String(str) = &event["location"] {
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
+    /// The path of the api on the instance, e.g. `/api/public/v1/`.
+    pub fn api_path(&self) -> &str {
+        self.instance.api_path()
+    }
 
-        let facility = if let Value::String(str) = &event["facility"] {
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
 
-        let source = if let Value::String(str) = &event["source"] {
-            Some(str.parse().unwrap())
-        } else {
-            None
+    /// When the oldest response served from the cache instead of Hack Club Mail was saved, if any
+    /// were.
+    pub fn cached_at(&self) -> Option<DateTime<Utc>> {
+        *self.cached_at.lock().unwrap()
+    }
+
+    /// Like [`MailClient::cached_at`], but forgets it, so something that fetches again and again can
+    /// tell whether the latest fetch was answered from the cache.
+    pub fn take_cached_at(&self) -> Option<DateTime<Utc>> {
+        self.cached_at.lock().unwrap().take()
+    }
+
+    /// How the requests sent to Hack Club Mail so far went.
+    pub fn request_metrics(&self) -> RequestMetrics {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Gets `url` from the cache or Hack Club Mail. With a cache, fresh enough responses are
+    /// served from it, new ones are saved to it, and unless
+    /// [`MailClientBuilder::stale_fallback`] is off it's fallen back on when Hack Club Mail can't
+    /// be reached.
+    async fn get_json(&self, url: Url) -> Result<Value, MailError> {
+        let Some(cache) = &self.cache else {
+            return self.fetch_json(url).await;
         };
+        let key = url.as_str();
+        if self.offline {
+            let cached = cache.get(key).ok_or(MailError::NotCached)?;
+            return Ok(self.serve_cached(cached));
+        }
+        if let Some(max_age) = self.max_age
+            && let Some(cached) = cache.get(key)
+            && cached.age() <= max_age
+        {
+            return Ok(self.serve_cached(cached));
+        }
 
-        Event {
-            happened_at,
-            description,
-            location,
-            facility,
-            source,
+        match self.fetch_json(url.clone()).await {
+            Ok(value) => {
+                // a cache that can't be written to only means there's nothing to fall back on
+                let _ = cache.put(key, &value);
+                Ok(value)
+            }
+            Err(err) if err.is_transient() && self.stale_fallback => match cache.get(key) {
+                Some(cached) => Ok(self.serve_cached(cached)),
+                None => Err(err),
+            },
+            Err(err) => Err(err),
         }
     }
 
-    fn letter_from_data(&self, letter: &Value) -> Option<Letter> {
-        let mut letter_exists = false;
-        let id = if let Value::String(str) = &letter["id"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let title = if let Value::String(str) = &letter["title"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let created_at: Option<DateTime<Utc>> = if let Value::String(str) = &letter["created_at"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let updated_at: Option<DateTime<Utc>> = if let Value::String(str) = &letter["updated_at"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let public_url = if let Value::String(str) = &letter["public_url"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let letter_type = if let Value::String(str) = &letter["type"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let letter_subtype = if let Value::String(str) = &letter["subtype"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let status = if let Value::String(str) = &letter["status"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let path = if let Value::String(str) = &letter["path"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let tags = if let Value::Array(tags) = &letter["tags"] {
-            letter_exists = true;
-            let mut tags_final = Vec::<String>::new();
-            for tag in tags.iter() {
-                tags_final.push(tag.to_string());
+    fn serve_cached(&self, cached: CachedResponse) -> Value {
+        let mut oldest = self.cached_at.lock().unwrap();
+        if oldest.is_none_or(|oldest| cached.cached_at < oldest) {
+            *oldest = Some(cached.cached_at);
+        }
+        cached.body
+    }
+
+    /// Gets `url` from Hack Club Mail, retrying connection problems, timeouts, 5xx answers and rate limits as the
+    /// [`Retry`] policy allows. Every try waits its turn with the rate limiter.
+    async fn fetch_json(&self, url: Url) -> Result<Value, MailError> {
+        let mut attempt = 0;
+        loop {
+            self.limiter.wait().await;
+            let started = Instant::now();
+            let result = self.try_get_json(url.clone()).await;
+            self.requests
+                .lock()
+                .unwrap()
+                .record(started.elapsed(), result.as_ref().err().map(|(err, _)| err));
+            let (err, retry_after) = match result {
+                Ok(value) => return Ok(value),
+                Err(failure) => failure,
+            };
+
+            let retryable = match &err {
+                MailError::Network(err) => err.is_connect() || err.is_timeout() || err.is_request(),
+                MailError::Server(_) | MailError::RateLimited => true,
+                _ => false,
+            };
+            attempt += 1;
+            if !retryable || attempt > self.retry.max_retries {
+                return Err(err);
             }
-            Some(tags_final)
-        } else {
-            None
-        };
-        let tracking_number = if let Value::String(str) = &letter["tracking_number"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let tracking_link = if let Value::String(str) = &letter["tracking_link"] {
-            letter_exists = true;
-            Some(str.parse().unwrap())
-        } else {
-            None
-        };
-        let events = if let Value::Array(events) = &letter["events"] {
-            letter_exists = true;
-            let mut events_final: Vec<Event> = Vec::new();
-            for event in events {
-                events_final.push(self.event_from_data(event));
+            let delay = retry_after.unwrap_or_else(|| self.retry.delay(attempt));
+            if delay > self.retry.max_delay {
+                return Err(err);
             }
+            tokio::time::sleep(delay).await;
+        }
+    }
 
-            Some(events_final)
-        } else {
-            None
-        };
+    /// A single try at getting `url`, along with how long the server asked to wait if it didn't
+    /// work out.
+    async fn try_get_json(&self, url: Url) -> Result<Value, (MailError, Option<Duration>)> {
+        let mut request = self.client.get(url).bearer_auth(&self.auth_token);
+        if let Some(timeout) = self.timeout {
+            request = request.timeout(timeout);
+        }
+        let response = request.send().await.map_err(|err| (err.into(), None))?;
 
-        if letter_exists {
-            Some(Letter {
-                id,
-                title,
-                created_at,
-                updated_at,
-                public_url,
-                status,
-                tags,
-                letter_type,
-                events,
-                letter_subtype,
-                path,
-                tracking_link,
-                tracking_number,
-            })
-        } else {
-            None
+        let status = response.status();
+        if !status.is_success() {
+            let retry_after = response
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(parse_retry_after);
+            return Err((MailError::from_status(status), retry_after));
         }
+
+        let body = response.text().await.map_err(|err| (err.into(), None))?;
+        serde_json::from_str(&body).map_err(|err| (err.into(), None))
     }
 
-    pub async fn get_id(&self) -> Result<Option<String>, Error> {
-        let body = self
-            .client
-            .get(format!("{}/{}/me", self.base, self.api_path))
-            .bearer_auth(&self.auth_token)
-            .send()
-            .await?
-            .text()
-            .await?;
+    pub async fn get_id(&self) -> Result<String, MailError> {
+        let data = self.get_json(self.instance.api_url(&["me"])).await?;
 
-        let data: Result<Value, serde_json::Error> = serde_json::from_str(&body);
+        match &data["user"]["id"] {
+            Value::String(id) => Ok(id.clone()),
+            Value::Number(id) => Ok(id.to_string()),
+            _ => Err(MailError::Schema(String::from("user.id"))),
+        }
+    }
+
+    pub async fn get_mail(&self, mail_type: Option<MailType>) -> Result<MailList, MailError> {
+        let (endpoint, name) = match mail_type {
+            Some(MailType::Letter) => ("letters", "letters"),
+            Some(MailType::Package) => ("packages", "packages"),
+            Some(MailType::Legacy) => ("lsv", "legacy_shipment_viewer_records"),
+            None => ("mail", "mail"),
+        };
 
-        if let Ok(data) = data {
-            Ok(Some(data["user"]["id"].to_string()))
+        let data = self.get_json(self.instance.api_url(&[endpoint])).await?;
+
+        if let Some(Value::Array(arr)) = data.get(name) {
+            let mut mail = MailList::default();
+            for (index, letter) in arr.iter().enumerate() {
+                match Letter::from_value(index, letter) {
+                    Ok(letter) => mail.letters.push(letter),
+                    Err(err) => mail.errors.push(err),
+                }
+            }
+            Ok(mail)
         } else {
-            Ok(None)
+            Err(MailError::Schema(String::from(name)))
         }
     }
 
-    pub async fn get_mail(
+    /// Like [`MailClient::get_mail`], but also fetches each piece of mail on its own (a few at a
+    /// time, see [`MailClientBuilder::concurrency`]) to fill in the events the list endpoints
+    /// leave out. Mail whose details can't be found, decoded or
+    /// (offline) found in the cache is kept as it was in the list.
+    pub async fn get_mail_with_events(
         &self,
         mail_type: Option<MailType>,
-    ) -> Result<Option<Vec<Letter>>, Error> {
-        let body = match mail_type {
-            Some(MailType::Legacy) => {
-                self.client
-                    .get(format!("{}/{}/lsv", self.base, self.api_path))
-                    .bearer_auth(&self.auth_token)
-                    .send()
-                    .await?
-                    .text()
-                    .await?
-            }
-            Some(MailType::Package) => {
-                self.client
-                    .get(format!("{}/{}/packages", self.base, self.api_path))
-                    .bearer_auth(&self.auth_token)
-                    .send()
-                    .await?
-                    .text()
-                    .await?
+    ) -> Result<MailList, MailError> {
+        let mut mail = self.get_mail(mail_type).await?;
+        let details: Vec<_> = stream::iter(&mail.letters)
+            .map(|letter| async move {
+                match (&letter.events, &letter.path) {
+                    (None, Some(path)) => Some(self.get_mail_by_path(path.clone()).await),
+                    _ => None,
+                }
+            })
+            .buffered(self.concurrency)
+            .collect()
+            .await;
+
+        for (letter, details) in mail.letters.iter_mut().zip(details) {
+            match details {
+                Some(Ok(details)) => {
+                    *letter = Letter {
+                        path: details.path.or(letter.path.take()),
+                        ..details
+                    }
+                }
+                Some(Err(
+                    MailError::NotFound
+                    | MailError::NotCached
+                    | MailError::Record(_)
+                    | MailError::Schema(_),
+                ))
+                | None => {}
+                Some(Err(err)) => return Err(err),
             }
-            Some(MailType::Letter) => {
-                self.client
-                    .get(format!("{}/{}/letters", self.base, self.api_path))
-                    .bearer_auth(&self.auth_token)
-                    .send()
-                    .await?
-                    .text()
-                    .await?
+        }
+        Ok(mail)
+    }
+
+    pub async fn get_mail_by_path(&self, path: String) -> Result<Letter, MailError> {
+        let data = self.get_json(self.instance.url(&path)).await?;
+        Self::record_from_data(&data)
+    }
+
+    pub async fn get_letter(&self, id: &str) -> Result<Letter, MailError> {
+        let data = self
+            .get_json(self.instance.api_url(&["letters", id]))
+            .await?;
+        Self::record_from_data(&data)
+    }
+
+    pub async fn get_package(&self, id: &str) -> Result<Letter, MailError> {
+        let data = self
+            .get_json(self.instance.api_url(&["packages", id]))
+            .await?;
+        Self::record_from_data(&data)
+    }
+
+    /// Legacy shipment viewer records are keyed by their type as well as their id.
+    pub async fn get_legacy(&self, lsv_type: &str, id: &str) -> Result<Letter, MailError> {
+        let data = self
+            .get_json(self.instance.api_url(&["lsv", lsv_type, id]))
+            .await?;
+        Self::record_from_data(&data)
+    }
+
+    /// Looks up a single piece of mail, going straight to its endpoint when the kind is known
+    /// (from `mail_type` or the id prefix). Legacy ids are written as `type/id`. Anything else
+    /// falls back to searching `/mail` for the id.
+    pub async fn get_by_id(
+        &self,
+        id: &str,
+        mail_type: Option<MailType>,
+    ) -> Result<Letter, MailError> {
+        match mail_type.or_else(|| MailType::from_id(id)) {
+            Some(MailType::Letter) => self.get_letter(id).await,
+            Some(MailType::Package) => self.get_package(id).await,
+            Some(MailType::Legacy) if id.contains('/') => {
+                let (lsv_type, id) = id.split_once('/').unwrap();
+                self.get_legacy(lsv_type, id).await
             }
             _ => {
-                self.client
-                    .get(format!("{}/{}/mail", self.base, self.api_path))
-                    .bearer_auth(&self.auth_token)
-                    .send()
-                    .await?
-                    .text()
+                let path = self
+                    .get_mail(None)
                     .await?
+                    .letters
+                    .into_iter()
+                    .find(|letter| letter.id.as_deref() == Some(id))
+                    .ok_or(MailError::NotFound)?
+                    .path
+                    .ok_or_else(|| MailError::Schema(String::from("path")))?;
+                self.get_mail_by_path(path).await
             }
-        };
-
-        let data: Result<Value, serde_json::Error> = serde_json::from_str(&body);
-
-        if let Ok(data) = data {
-            let mut mail: Vec<Letter> = Vec::new();
+        }
+    }
 
-            let name = match mail_type {
-                Some(MailType::Letter) => "letters",
-                Some(MailType::Package) => "packages",
-                Some(MailType::Legacy) => "legacy_shipment_viewer_records",
-                None => "mail",
-            };
+    /// Looks up several pieces of mail like [`MailClient::get_by_id`], a few at a time (see
+    /// [`MailClientBuilder::concurrency`]). The results are in the same order as `ids`.
+    pub async fn get_many(
+        &self,
+        ids: &[String],
+        mail_type: Option<MailType>,
+    ) -> Vec<Result<Letter, MailError>> {
+        stream::iter(ids)
+            .map(|id| self.get_by_id(id, mail_type))
+            .buffered(self.concurrency)
+            .collect()
+            .await
+    }
 
-            if let Some(Value::Array(arr)) = data.get(name) {
-                for letter in arr.iter() {
-                    // unwrapping since this will not break on this
-                    mail.push(self.letter_from_data(letter).unwrap());
-                }
-                Ok(Some(mail))
-            } else {
-                Ok(None)
+    fn record_from_data(data: &Value) -> Result<Letter, MailError> {
+        for name in ["letter", "package", "legacy_shipment_viewer_record"] {
+            if let Value::Object(_) = &data[name] {
+                return Ok(Letter::from_value(0, &data[name])?);
             }
-        } else {
-            Ok(None)
         }
+        Err(MailError::Schema(String::from("letter")))
     }
+}
 
-    pub async fn get_mail_by_path(&self, path: String) -> Result<Option<Letter>, Error> {
-        let body = self
-            .client
-            .get(format!("{}/{path}", self.base))
-            .bearer_auth(&self.auth_token)
-            .send()
-            .await?
-            .text()
-            .await?;
+impl MailClientBuilder {
+    /// Sends requests to another instance instead of `https://mail.hackclub.com`.
+    pub fn base_url(mut self, base: impl Into<String>) -> Self {
+        self.base = Some(base.into());
+        self
+    }
 
-        let data: Result<Value, serde_json::Error> = serde_json::from_str(&body);
-
-        if let Ok(data) = data {
-            if let Value::Object(_) = &data["letter"] {
-                Ok(self.letter_from_data(&data["letter"]))
-            } else if let Value::Object(_) = &data["package"] {
-                Ok(self.letter_from_data(&data["package"]))
-            } else if let Value::Object(_) = &data["legacy_shipment_viewer_record"] {
-                Ok(self.letter_from_data(&data["legacy_shipment_viewer_record"]))
-            } else {
-                Ok(None)
-            }
-        } else {
-            Ok(None)
+    /// Changes where the api lives on the instance. Defaults to `/api/public/v1/`.
+    pub fn api_path(mut self, api_path: impl Into<String>) -> Self {
+        self.api_path = Some(api_path.into());
+        self
+    }
+
+    /// Gives up on a request after `timeout`, [`DEFAULT_TIMEOUT`] unless it's set.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.client.timeout = Some(timeout);
+        self
+    }
+
+    /// Changes how failed requests are retried. [`Retry::none`] turns retrying off.
+    pub fn retry(mut self, retry: Retry) -> Self {
+        self.client.retry = retry;
+        self
+    }
+
+    /// Saves responses to `cache` and falls back on them when Hack Club Mail can't be reached
+    /// (see [`MailClientBuilder::stale_fallback`]). [`MailClient::cached_at`] tells when that
+    /// happened.
+    pub fn cache(mut self, cache: Cache) -> Self {
+        self.client.cache = Some(cache);
+        self
+    }
+
+    /// Only answers from the cache, failing with [`MailError::NotCached`] for anything that isn't
+    /// in it. Does nothing without a cache.
+    pub fn offline(mut self, offline: bool) -> Self {
+        self.client.offline = offline;
+        self
+    }
+
+    /// Whether errors that might go away on their own are answered with the cached response
+    /// instead, on by default. Things that keep polling, like [`Watcher`](crate::Watcher), should
+    /// turn it off so they hear about outages instead of seeing old mail as nothing changing.
+    pub fn stale_fallback(mut self, stale_fallback: bool) -> Self {
+        self.client.stale_fallback = stale_fallback;
+        self
+    }
+
+    /// Answers from the cache without asking Hack Club Mail when the cached response is at most
+    /// `max_age` old.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.client.max_age = Some(max_age);
+        self
+    }
+
+    /// Sends at most `per_second` requests a second. There's no limit by default, and 0 turns it
+    /// back off.
+    pub fn rate_limit(mut self, per_second: u32) -> Self {
+        self.client.limiter = RateLimiter::new(per_second);
+        self
+    }
+
+    /// How many requests [`MailClient::get_many`] and [`MailClient::get_mail_with_events`] have
+    /// going at once, [`DEFAULT_CONCURRENCY`] unless it's set. It's never less than 1.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.client.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Uses an existing reqwest client, e.g. one with a proxy or custom tls set up.
+    pub fn client(mut self, client: Client) -> Self {
+        self.client.client = client;
+        self
+    }
+
+    /// Finishes the client, failing with [`MailError::InvalidUrl`] if the base url isn't one.
+    pub fn build(mut self) -> Result<MailClient, MailError> {
+        if self.base.is_some() || self.api_path.is_some() {
+            self.client.instance = Instance::new(
+                self.base.as_deref().unwrap_or(DEFAULT_BASE),
+                self.api_path.as_deref().unwrap_or(DEFAULT_API_PATH),
+            )?;
         }
+        Ok(self.client)
     }
 }
 
@@ -328,8 +769,17 @@ impl Default for MailClient {
     fn default() -> Self {
         Self {
             auth_token: String::new(),
-            base: String::from("https://mail.hackclub.com"),
-            api_path: String::from("/api/public/v1/"),
+            instance: Instance::default(),
+            timeout: Some(DEFAULT_TIMEOUT),
+            retry: Retry::default(),
+            limiter: RateLimiter::default(),
+            cache: None,
+            offline: false,
+            max_age: None,
+            stale_fallback: true,
+            cached_at: Mutex::new(None),
+            requests: Mutex::default(),
+            concurrency: DEFAULT_CONCURRENCY,
             client: Client::new(),
         }
     }
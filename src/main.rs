@@ -1,305 +1,736 @@
 use core::time;
+use std::env;
+use std::io;
+use std::path::PathBuf;
 use std::thread::sleep;
+use std::time::Duration;
+use tokio::signal;
 
-use anyhow::Error;
+use anyhow::{Context, Error, bail};
+use chrono::{Local, Utc};
 use clap::Parser;
-use cli::{Cli, Config};
-use cli_table::{Cell, CellStruct, Table};
-use confy::{ConfyError, load, store};
-use mail::{Letter, MailClient};
+use cli::{
+    Cli, Command, Config, DEFAULT_PROFILE, ExportCommand, FeedFormat, Format, KeyOptions,
+    NetworkArgs, Profile, ProfileCommand,
+};
+use confy::ConfyError;
+use orphy::watch::backoff;
+use orphy::{
+    Cache, Change, MailClient, MailError, Notifier, Retry, Stats, Store, Watcher, Webhook,
+};
+use orphy::{feed, ics};
+use output::{ChangeStream, ProfileRow, Summary, ViewMode};
+use sha2::{Digest, Sha256};
 
 mod cli;
-mod mail;
+mod output;
+mod secret;
+mod serve;
+mod tui;
 
 #[tokio::main]
-async fn main() -> Result<(), Error> {
-    let cfg: Result<Config, ConfyError> = load("orphy_hackclub_mail_client", None);
-    let args = Cli::parse();
-
-    match args {
-        Cli::Setup { api_key } => {
-            let cfg = Config { api_key };
-            match store("orphy_hackclub_mail_client", None, cfg) {
-                Ok(_) => println!("Saved your api key!"),
-                Err(_) => eprintln!("There was an error! [4]"),
+async fn main() {
+    if let Err(err) = run().await {
+        eprintln!("{err}");
+        // errors that already say what caused them don't need it said again
+        let mut shown = err.to_string();
+        for cause in err.chain().skip(1) {
+            let cause = cause.to_string();
+            if !shown.contains(&cause) {
+                eprintln!("caused by: {cause}");
             }
+            shown = cause;
         }
-        Cli::Mail { r#type } => {
-            if let Ok(cfg) = cfg {
-                if cfg.api_key.is_empty() {
-                    println!("No api key! Add one using orphy setup [api key]");
-                    return Ok(());
-                }
-                let client = MailClient::new(cfg.api_key);
-                println!("Loading your mail...");
-                let letters = client.get_mail(r#type).await;
-                if let Ok(Some(letters)) = letters {
-                    if !letters.is_empty() {
-                        let mut table: Vec<Vec<CellStruct>> = Vec::new();
-                        for letter in letters {
-                            let mut row: Vec<CellStruct> = Vec::new();
-                            if let Some(title) = letter.title {
-                                row.push(title.cell());
-                            } else if let Some(tags) = letter.tags {
-                                let mut title = String::from("letter: ");
-                                tags.iter().enumerate().for_each(|x| {
-                                    title += x.1;
-                                    if x.0 < tags.len() - 1 {
-                                        title += ", ";
-                                    }
-                                });
-                                row.push(title.cell());
-                            }
-                            if let Some(letter_type) = letter.letter_type {
-                                row.push(letter_type.cell())
-                            } else {
-                                row.push(String::from("no type").cell())
-                            }
-                            if let Some(id) = letter.id {
-                                row.push(id.cell())
-                            } else {
-                                row.push(String::from("no id").cell())
-                            }
-                            if let Some(status) = letter.status {
-                                row.push(status.cell());
-                            } else {
-                                row.push(String::from("no status").cell())
-                            }
-                            if let Some(created_at) = letter.created_at {
-                                row.push(created_at.date_naive().to_string().cell());
-                            } else {
-                                row.push(String::from("no date").cell())
-                            }
-                            table.push(row);
-                        }
-                        let table = table
-                            .table()
-                            .title(vec!["Name", "Type", "ID", "Status", "Creation Date"])
-                            .display()
-                            .unwrap();
+        let code = err
+            .downcast_ref::<MailError>()
+            .map_or(1, MailError::exit_code);
+        std::process::exit(code);
+    }
+}
 
-                        println!("{}", table);
-                        println!(
-                            "View more details by using orphy view --id (put the id of your letter here!)"
-                        )
-                    } else {
-                        println!("You don't have any mail!")
-                    }
-                } else {
-                    eprintln!("There was an error [1]! api key might be invalid!");
-                }
-            } else {
-                eprintln!(
-                    "You don't have an api key! Run orphy setup [your api key] with your api key."
-                )
+/// How requests are made, from the command line flags and then the config.
+struct Network {
+    instance: Option<String>,
+    timeout: Option<Duration>,
+    retries: Option<u32>,
+    rate_limit: Option<u32>,
+    offline: bool,
+    max_age: Option<Duration>,
+    concurrency: Option<u16>,
+    /// Whether old cached mail is shown when Hack Club Mail can't be reached. Commands that keep
+    /// polling turn this off so outages are reported instead.
+    stale_fallback: bool,
+}
+
+impl Network {
+    fn new(cli: &NetworkArgs, cfg: Option<&Config>) -> Result<Self, Error> {
+        let timeout = match (cli.timeout, cfg.and_then(|cfg| cfg.timeout.as_deref())) {
+            (Some(timeout), _) => Some(timeout),
+            (None, Some(timeout)) => {
+                Some(humantime::parse_duration(timeout).with_context(|| {
+                    format!("The timeout in your config (`{timeout}`) isn't a duration like 30s")
+                })?)
             }
+            (None, None) => None,
+        };
+        Ok(Self {
+            instance: cli.instance.clone(),
+            timeout,
+            retries: cli.retries.or(cfg.and_then(|cfg| cfg.retries)),
+            rate_limit: cli.rate_limit.or(cfg.and_then(|cfg| cfg.rate_limit)),
+            offline: cli.offline,
+            max_age: cli.max_age,
+            concurrency: cli.concurrency,
+            stale_fallback: true,
+        })
+    }
+}
+
+/// Builds the client for an account. The instance is the first of `--instance`, `ORPHY_INSTANCE`
+/// and the profile's own that's set, and the same goes for `ORPHY_API_PATH` and the api path.
+fn mail_client(
+    api_key: String,
+    profile: &Profile,
+    network: &Network,
+    cache: Option<Cache>,
+) -> Result<MailClient, MailError> {
+    let mut builder = MailClient::builder(api_key);
+    if let Some(base) = network
+        .instance
+        .clone()
+        .or_else(|| env::var("ORPHY_INSTANCE").ok())
+        .or_else(|| profile.base.clone())
+    {
+        builder = builder.base_url(base);
+    }
+    if let Some(api_path) = env::var("ORPHY_API_PATH")
+        .ok()
+        .or_else(|| profile.api_path.clone())
+    {
+        builder = builder.api_path(api_path);
+    }
+    if let Some(timeout) = network.timeout {
+        builder = builder.timeout(timeout);
+    }
+    if let Some(max_retries) = network.retries {
+        builder = builder.retry(Retry {
+            max_retries,
+            ..Retry::default()
+        });
+    }
+    if let Some(per_second) = network.rate_limit {
+        builder = builder.rate_limit(per_second);
+    }
+    if let Some(concurrency) = network.concurrency {
+        builder = builder.concurrency(concurrency.into());
+    }
+    if let Some(cache) = cache {
+        builder = builder
+            .cache(cache)
+            .offline(network.offline)
+            .stale_fallback(network.stale_fallback);
+        if let Some(max_age) = network.max_age {
+            builder = builder.max_age(max_age);
         }
-        Cli::View { id } => {
-            if let Ok(cfg) = cfg {
-                if cfg.api_key.is_empty() {
-                    println!("No api key! Add one using orphy setup [api key]");
-                    return Ok(());
-                }
-                let client = MailClient::new(cfg.api_key);
-                println!("Loading your mail...");
-                let mail_list = client.get_mail(None).await;
-                let mut mail = Ok(Some(Letter::default()));
-                let mut letter_exists = false;
-                if let Ok(Some(mail_list)) = mail_list {
-                    for current_mail in mail_list {
-                        if let Some(current_id) = current_mail.id {
-                            if current_id == id {
-                                if let Some(path) = current_mail.path {
-                                    mail = client.get_mail_by_path(path).await;
-                                    letter_exists = true;
-                                    break;
-                                } else {
-                                    eprintln!("There was an error [2]! api key might be invalid!");
-                                }
-                            }
-                        }
-                    }
-                }
-                if !letter_exists {
-                    eprintln!("Letter doesn't exist or api key may be invalid!")
-                } else if let Ok(mail) = mail {
-                    if let Some(letter) = mail {
-                        let mut table: Vec<Vec<CellStruct>> = Vec::new();
-                        if let Some(id) = letter.id {
-                            table.push(vec!["ID".cell(), id.cell()]);
-                        } else {
-                            table.push(vec!["ID".cell(), "no id".cell()]);
-                        }
-                        if let Some(title) = letter.title {
-                            table.push(vec!["Name".cell(), title.cell()]);
-                        } else if let Some(tags) = letter.tags {
-                            let mut name = String::from("letter: ");
-                            for (i, e) in tags.iter().enumerate() {
-                                name += e;
-                                if i < tags.len() - 1 {
-                                    name += ", "
-                                }
-                            }
-                            table.push(vec!["Name".cell(), name.cell()])
-                        } else {
-                            table.push(vec!["Name".cell(), "no name".cell()])
-                        }
-                        if let Some(letter_type) = letter.letter_type {
-                            table.push(vec!["Type".cell(), letter_type.cell()]);
-                        } else {
-                            table.push(vec!["Type".cell(), "no type".cell()]);
-                        }
-                        if let Some(letter_subtype) = letter.letter_subtype {
-                            table.push(vec!["Subtype".cell(), letter_subtype.cell()]);
-                        }
-                        if let Some(status) = letter.status {
-                            table.push(vec!["Status".cell(), status.cell()]);
-                        } else {
-                            table.push(vec!["Status".cell(), "no status".cell()]);
-                        }
-                        if let Some(created_at) = letter.created_at {
-                            table.push(vec!["Created At".cell(), created_at.cell()]);
-                        } else {
-                            table.push(vec!["Created At".cell(), "no creation date".cell()]);
-                        }
-                        if let Some(updated_at) = letter.updated_at {
-                            table.push(vec!["Updated At".cell(), updated_at.cell()]);
-                        }
-                        if let Some(public_url) = letter.public_url {
-                            table.push(vec!["Public URL".cell(), public_url.cell()]);
-                        }
-                        if let Some(tracking_number) = letter.tracking_number {
-                            table.push(vec!["Tracking Number".cell(), tracking_number.cell()]);
-                        }
-                        if let Some(tracking_link) = letter.tracking_link {
-                            table.push(vec!["Tracking Link".cell(), tracking_link.cell()]);
-                        }
+    }
+    builder.build()
+}
 
-                        let table = table.table().display().unwrap();
-                        println!("{}", table);
+/// Builds the client for the selected profile, failing with [`MailError::NoApiKey`] when there's
+/// no api key for it.
+fn connect(
+    cfg: &Result<Config, ConfyError>,
+    selected: Option<&str>,
+    network: &Network,
+) -> Result<(String, MailClient), Error> {
+    let cfg = readable(cfg)?;
+    let name = cfg.profile_name(selected);
+    let profile = match cfg.profiles.get(&name) {
+        Some(profile) => profile.clone(),
+        None if name != DEFAULT_PROFILE => bail!(
+            "There's no profile named {name}! Add one using orphy profile add {name} [api key]"
+        ),
+        None => Profile::default(),
+    };
+    let api_key = match secret::env_api_key() {
+        Some(api_key) => api_key,
+        None => match secret::api_key(&profile)? {
+            Some(api_key) => api_key,
+            None => return Err(MailError::NoApiKey.into()),
+        },
+    };
+    let cache = cache(&name, &api_key)?;
+    let client = mail_client(api_key, &profile, network, Some(cache))?;
+    Ok((name, client))
+}
 
-                        if let Some(events) = letter.events {
-                            if !events.is_empty() {
-                                println!("Events");
-                                for event in events {
-                                    let mut table: Vec<Vec<CellStruct>> = Vec::new();
-                                    if let Some(source) = event.source {
-                                        table.push(vec!["Source".cell(), source.cell()]);
-                                    } else {
-                                        table.push(vec!["Source".cell(), "no source".cell()]);
-                                    }
+/// The config, or why it couldn't be read.
+fn readable(cfg: &Result<Config, ConfyError>) -> Result<&Config, Error> {
+    cfg.as_ref()
+        .map_err(|err| anyhow::anyhow!("Couldn't read orphy's config file: {err}"))
+}
 
-                                    if let Some(facility) = event.facility {
-                                        table.push(vec!["Facility".cell(), facility.cell()]);
-                                    } else {
-                                        table.push(vec!["Facility".cell(), "no facility".cell()]);
-                                    }
+/// Saves an api key into `profile` the way `key` asks for, after checking it works.
+async fn save_key(
+    profile: &mut Profile,
+    api_key: Option<String>,
+    key: KeyOptions,
+    network: &Network,
+) -> Result<(), Error> {
+    let api_key = match (&key.token_command, api_key) {
+        (Some(command), _) => secret::run_token_command(command)?,
+        (None, Some(api_key)) => api_key,
+        (None, None) => bail!("No api key given"),
+    };
 
-                                    if let Some(description) = event.description {
-                                        table.push(vec!["Description".cell(), description.cell()]);
-                                    } else {
-                                        table.push(vec![
-                                            "Description".cell(),
-                                            "no description".cell(),
-                                        ]);
-                                    }
+    if !key.no_verify {
+        let id = mail_client(api_key.clone(), profile, network, None)?
+            .get_id()
+            .await
+            .context("Couldn't verify your api key (pass --no-verify to save it anyway)")?;
+        println!("Your api key belongs to {id}");
+    }
 
-                                    if let Some(location) = event.location {
-                                        table.push(vec!["Location".cell(), location.cell()]);
-                                    } else {
-                                        table.push(vec!["Location".cell(), "no location".cell()]);
-                                    }
+    profile.api_key.clear();
+    profile.encrypted_key = None;
+    profile.token_command = None;
+    if let Some(command) = key.token_command {
+        profile.token_command = Some(command);
+    } else if key.encrypt {
+        profile.encrypted_key = Some(secret::encrypt(&api_key, &secret::new_passphrase()?)?);
+    } else {
+        profile.api_key = api_key;
+    }
+    Ok(())
+}
 
-                                    if let Some(happened_at) = event.happened_at {
-                                        table.push(vec!["Happened At".cell(), happened_at.cell()]);
-                                    } else {
-                                        table.push(vec![
-                                            "Happened At".cell(),
-                                            "no happening date".cell(),
-                                        ]);
-                                    }
+/// Where the mail history of a profile lives, next to the config file.
+fn history_path(profile: &str) -> Result<PathBuf, Error> {
+    let config = Config::path()?;
+    Ok(if profile == DEFAULT_PROFILE {
+        config.with_file_name("history.sqlite3")
+    } else {
+        config.with_file_name(format!("history-{profile}.sqlite3"))
+    })
+}
 
-                                    let table = table.table().display().unwrap();
+/// Where the responses of a profile are cached, next to the config file. Every api key gets its
+/// own folder, named after a hash of it, so a key from `ORPHY_API_KEY` doesn't see what another
+/// account cached.
+fn cache(profile: &str, api_key: &str) -> Result<Cache, Error> {
+    let key: String = Sha256::digest(api_key.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    Ok(Cache::new(cache_dir(profile)?.join(key)))
+}
 
-                                    println!("{}", table);
-                                }
-                            }
-                        }
-                    } else {
-                        eprintln!("Your letter does not exist!");
+fn cache_dir(profile: &str) -> Result<PathBuf, Error> {
+    Ok(Config::path()?.with_file_name("cache").join(profile))
+}
+
+/// Deletes the history and cached mail of a profile, if it has any.
+fn remove_data(profile: &str) -> Result<(), Error> {
+    let history = history_path(profile)?;
+    match std::fs::remove_file(&history) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            return Err(err).with_context(|| format!("Couldn't delete {}", history.display()));
+        }
+        _ => {}
+    }
+    let cache = cache_dir(profile)?;
+    match std::fs::remove_dir_all(&cache) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => {
+            Err(err).with_context(|| format!("Couldn't delete {}", cache.display()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Says so when some of what's about to be shown came from the cache instead of Hack Club Mail.
+fn warn_if_cached(client: &MailClient) {
+    if let Some(cached_at) = client.cached_at() {
+        eprintln!(
+            "Showing mail cached at {}, it might be out of date.",
+            cached_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+        );
+    }
+}
+
+/// Counts up the mail of one profile for `orphy fetch`.
+async fn summarize(profile: String, client: &MailClient) -> Result<Summary, Error> {
+    let mail = client.get_mail(None).await?;
+    for err in &mail.errors {
+        eprintln!("Skipping {err}");
+    }
+    let letters = mail.letters;
+    let mut letter_count = 0;
+    let mut package_count = 0;
+    let mut legacy_count = 0;
+    letters.iter().for_each(|x| {
+        if x.letter_type == Some(String::from("letter")) {
+            letter_count += 1;
+        } else if x.letter_type == Some(String::from("package")) {
+            package_count += 1;
+        } else {
+            legacy_count += 1;
+        }
+    });
+    Ok(Summary {
+        profile,
+        id: client.get_id().await.ok(),
+        instance: client.base().to_string(),
+        last_mailed: letters.first().and_then(|letter| letter.created_at),
+        mail: letters.len(),
+        letters: letter_count,
+        packages: package_count,
+        legacy: legacy_count,
+    })
+}
+
+/// Sends every change to every webhook. Failures are reported but don't stop orphy.
+async fn deliver(webhooks: &[Webhook], changes: &[Change]) {
+    if webhooks.is_empty() || changes.is_empty() {
+        return;
+    }
+    let client = reqwest::Client::new();
+    for webhook in webhooks {
+        for change in changes {
+            if let Err(err) = webhook.send(&client, change).await {
+                eprintln!("{err}");
+            }
+        }
+    }
+}
+
+/// Polls `watcher` every `interval` until Ctrl-C, handing every batch of changes to
+/// `on_changes`. Errors that might go away on their own are retried with backoff.
+async fn watch(
+    client: &MailClient,
+    watcher: &mut Watcher,
+    interval: Duration,
+    mut on_changes: impl AsyncFnMut(&[Change]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut failures = 0;
+    let mut primed = false;
+    // one listener for the whole watch, so a Ctrl-C while changes are being handed out isn't lost
+    let ctrl_c = signal::ctrl_c();
+    tokio::pin!(ctrl_c);
+    loop {
+        let poll = async {
+            match watcher.poll(client).await {
+                Ok(changes) => {
+                    failures = 0;
+                    if !primed {
+                        eprintln!(
+                            "Watching {} pieces of mail. Press Ctrl-C to stop.",
+                            watcher.len()
+                        );
+                        primed = true;
+                    }
+                    on_changes(&changes).await?;
+                    Ok(interval)
+                }
+                Err(err) if err.is_transient() => {
+                    failures += 1;
+                    let delay = backoff(interval, failures);
+                    eprintln!(
+                        "{err} Trying again in {}.",
+                        humantime::format_duration(delay)
+                    );
+                    Ok(delay)
+                }
+                Err(err) => Err(Error::from(err)),
+            }
+        };
+        let delay = tokio::select! {
+            delay = poll => delay?,
+            _ = &mut ctrl_c => return Ok(()),
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = &mut ctrl_c => return Ok(()),
+        }
+    }
+}
+
+async fn run() -> Result<(), Error> {
+    let cfg = Config::load();
+    let Cli {
+        format,
+        profile,
+        network,
+        command,
+    } = Cli::parse();
+    let (format, feed_format) = match format.split() {
+        Ok(format) => (format, None),
+        Err(feed_format) => (Format::Table, Some(feed_format)),
+    };
+    if feed_format.is_some()
+        && !matches!(
+            command,
+            Command::Export {
+                command: ExportCommand::Feed { .. }
+            }
+        )
+    {
+        bail!("--format atom and --format rss only work with orphy export feed");
+    }
+    let profile = profile.as_deref();
+    let network = Network::new(&network, cfg.as_ref().ok())?;
+
+    match command {
+        Command::Setup { api_key, key } => {
+            let mut cfg = cfg.unwrap_or_default();
+            let name = cfg.profile_name(profile);
+            save_key(
+                cfg.profiles.entry(name).or_default(),
+                api_key,
+                key,
+                &network,
+            )
+            .await?;
+            cfg.save().context("Couldn't save your api key")?;
+            println!("Saved your api key!");
+        }
+        Command::Profile { command } => {
+            let mut cfg = cfg.context("Couldn't read your config")?;
+            match command {
+                ProfileCommand::Add {
+                    name,
+                    api_key,
+                    base,
+                    api_path,
+                    key,
+                } => {
+                    let mut new = Profile {
+                        base,
+                        api_path,
+                        ..Profile::default()
+                    };
+                    save_key(&mut new, api_key, key, &network).await?;
+                    if cfg.active.is_none() && !cfg.profiles.contains_key(DEFAULT_PROFILE) {
+                        cfg.active = Some(name.clone());
                     }
-                } else {
-                    eprintln!("There was an error [3]! api key might be invalid!");
+                    cfg.profiles.insert(name.clone(), new);
+                    cfg.save().context("Couldn't save your profile")?;
+                    println!("Saved profile {name}!");
+                }
+                ProfileCommand::List => {
+                    let active = cfg.profile_name(None);
+                    let rows: Vec<ProfileRow> = cfg
+                        .profiles
+                        .into_iter()
+                        .map(|(name, profile)| ProfileRow {
+                            active: name == active,
+                            name,
+                            base: profile.base,
+                        })
+                        .collect();
+                    output::print_profiles(format, &rows)?;
                 }
+                ProfileCommand::Use { name } => {
+                    if !cfg.profiles.contains_key(&name) {
+                        bail!("There's no profile named {name}!");
+                    }
+                    cfg.active = Some(name.clone());
+                    cfg.save().context("Couldn't save your config")?;
+                    println!("Using profile {name}");
+                }
+                ProfileCommand::Remove { name, keep_data } => {
+                    if cfg.profiles.remove(&name).is_none() {
+                        bail!("There's no profile named {name}!");
+                    }
+                    if cfg.active.as_deref() == Some(name.as_str()) {
+                        cfg.active = None;
+                    }
+                    cfg.save().context("Couldn't save your config")?;
+                    if !keep_data {
+                        remove_data(&name)?;
+                    }
+                    println!("Removed profile {name}");
+                }
+            }
+        }
+        Command::Mail { r#type, query } => {
+            let (_, client) = connect(&cfg, profile, &network)?;
+            if format == Format::Table {
+                println!("Loading your mail...");
+            }
+            let mail = client.get_mail(r#type).await?;
+            for err in &mail.errors {
+                eprintln!("Skipping {err}");
+            }
+            warn_if_cached(&client);
+            output::print_letters(format, query.apply(mail.letters))?;
+        }
+        Command::View {
+            id: mut ids,
+            all,
+            stdin,
+            r#type,
+            events_only,
+            latest_event,
+        } => {
+            let mode = if latest_event {
+                ViewMode::LatestEvent
+            } else if events_only {
+                ViewMode::EventsOnly
             } else {
-                eprintln!(
-                    "You don't have an api key! Run orphy setup [your api key] with your api key."
-                )
+                ViewMode::Full
+            };
+            let (_, client) = connect(&cfg, profile, &network)?;
+            if stdin {
+                ids.extend(
+                    io::read_to_string(io::stdin())
+                        .context("Couldn't read ids from stdin")?
+                        .split_whitespace()
+                        .map(String::from),
+                );
+            }
+            if format == Format::Table {
+                println!("Loading your mail...");
+            }
+
+            if let [id] = &ids[..]
+                && !stdin
+            {
+                let letter = client.get_by_id(id, r#type).await?;
+                warn_if_cached(&client);
+                output::print_letter(format, letter, mode)?;
+                return Ok(());
+            }
+
+            let mut letters = Vec::new();
+            let mut failures = Vec::new();
+            if all {
+                let mail = client.get_mail_with_events(r#type).await?;
+                for err in &mail.errors {
+                    eprintln!("Skipping {err}");
+                }
+                letters = mail.letters;
+            } else {
+                for (id, result) in ids.iter().zip(client.get_many(&ids, r#type).await) {
+                    match result {
+                        Ok(letter) => letters.push(letter),
+                        Err(err) => {
+                            eprintln!("Couldn't load {id}: {err}");
+                            failures.push(err);
+                        }
+                    }
+                }
+            }
+            warn_if_cached(&client);
+            // when every id failed there's nothing to show but the errors
+            if failures.is_empty() || !letters.is_empty() {
+                output::print_report(format, &letters, mode)?;
+            }
+
+            if !failures.is_empty() {
+                let count = failures.len();
+                return Err(Error::from(failures.remove(0)).context(format!(
+                    "Couldn't load {count} of {} pieces of mail",
+                    ids.len()
+                )));
             }
         }
-        Cli::Fetch => {
-            if let Ok(cfg) = cfg {
-                if cfg.api_key.is_empty() {
-                    println!("No api key! Add one using orphy setup [api key]");
-                    return Ok(());
+        Command::Stats { r#type } => {
+            let (_, client) = connect(&cfg, profile, &network)?;
+            if format == Format::Table {
+                println!("Loading your stats...");
+            }
+            let mail = client.get_mail_with_events(r#type).await?;
+            for err in &mail.errors {
+                eprintln!("Skipping {err}");
+            }
+            warn_if_cached(&client);
+            output::print_stats(format, &Stats::new(&mail.letters))?;
+        }
+        Command::Fetch { all_profiles: true } => {
+            let cfg = readable(&cfg)?;
+            if format == Format::Table {
+                println!("Loading your stats...");
+            }
+            let mut summaries = Vec::new();
+            for (name, profile) in &cfg.profiles {
+                let api_key = match secret::api_key(profile) {
+                    Ok(Some(api_key)) => api_key,
+                    Ok(None) => continue,
+                    Err(err) => {
+                        eprintln!("Skipping profile {name}: {err}");
+                        continue;
+                    }
+                };
+                let client = match cache(name, &api_key)
+                    .and_then(|cache| Ok(mail_client(api_key, profile, &network, Some(cache))?))
+                {
+                    Ok(client) => client,
+                    Err(err) => {
+                        eprintln!("Skipping profile {name}: {err}");
+                        continue;
+                    }
+                };
+                match summarize(name.clone(), &client).await {
+                    Ok(summary) => {
+                        warn_if_cached(&client);
+                        summaries.push(summary);
+                    }
+                    Err(err) => eprintln!("Skipping profile {name}: {err}"),
                 }
-                let client = MailClient::new(cfg.api_key);
+            }
+            output::print_summaries(format, &summaries)?;
+        }
+        Command::Fetch {
+            all_profiles: false,
+        } => {
+            let (name, client) = connect(&cfg, profile, &network)?;
+            if format == Format::Table {
                 println!("Loading your stats...");
-                let letters = client.get_mail(None).await;
-                if let Ok(Some(letters)) = letters {
-                    let first = letters.first();
-                    let mut letter_count = 0;
-                    let mut package_count = 0;
-                    let mut legacy_count = 0;
-                    letters.iter().for_each(|x| {
-                        if x.letter_type == Some(String::from("letter")) {
-                            letter_count += 1;
-                        } else if x.letter_type == Some(String::from("package")) {
-                            package_count += 1;
-                        } else {
-                            legacy_count += 1;
-                        }
-                    });
-                    println!(
-                        "
-⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⣠⡤⠶⠒⠛⠉⠙⠛⠒⠶⢤⣀⠀⠀⠀⠀⠀⠀
-⠀⠀⠀⠀⠀⠀⠀⠀⠀⢀⡴⠋⠁⠀⠀⠀⠀⠀⠀⠁⠀⠀⠀⠈⠙⢦⡀⠀⠀⠀ {}
-⠀⠀⠀⠀⣀⣠⠤⠤⡴⠻⢓⣶⠦⠤⣄⡀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠙⢦⡀⠀ instance: {}
-⣀⡠⠴⠊⠁⠀⠀⠀⠀⠀⠒⠽⠀⠀⠀⠉⢙⠒⢢⡄⠀⠀⠀⠀⠀⠀⠀⠈⢷⡀ {}
-⠘⢆⠉⠑⡆⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⢆⠁⣠⠎⠀⠀⠀⠀⠀⠀⠀⠀⠀⠈⣇ mail: {}
-⠀⠈⠓⠦⠁⢀⣀⠀⠀⠀⠀⠀⠀⣀⣀⢸⡊⠁⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⢻ letters: {}
-⠀⠀⠀⡸⠀⠉⠀⠙⠆⠀⠀⠀⠏⠀⠈⠀⢇⠀⠀⠀⠀⠀⠀⠀⢀⠔⠀⠀⠀⣼ packages: {}
-⠀⠀⠀⠹⣄⠀⠠⣤⡡⠪⡭⠃⡤⢤⡄⡰⠋⠀⠀⠀⣀⡠⠴⠊⠁⠀⠀⠀⢠⠇ legacy: {}
-⠀⠀⢠⢿⣠⠟⠓⠛⠉⠛⡟⠛⠛⠛⠛⠒⠒⠚⠉⠉⠁⠀⠀⠀⠀⠀⢀⡴⠋⠀
-⠀⠀⠈⠛⢯⣀⣀⣀⡤⠤⠤⠤⢤⣤⣀⣀⣀⣀⣀⣀⣀⣤⠤⠴⠒⠋⠁⠀⠀⠀ 
-",
-                        if let Ok(Some(id)) = client.get_id().await {
-                            format!("id: {id}")
-                        } else {
-                            String::from("")
-                        },
-                        client.base,
-                        if !letters.is_empty() && first.unwrap().created_at.is_some() {
-                            format!(
-                                "last mailed: {}",
-                                first.unwrap().created_at.unwrap().naive_utc()
-                            )
-                        } else {
-                            String::from("")
-                        },
-                        letters.len(),
-                        letter_count,
-                        package_count,
-                        legacy_count
-                    )
+            }
+            let summary = summarize(name, &client).await?;
+            warn_if_cached(&client);
+            output::print_summary(format, &summary)?;
+        }
+        Command::Export {
+            command: ExportCommand::Ics { output, r#type },
+        } => {
+            let (_, client) = connect(&cfg, profile, &network)?;
+            let mail = client.get_mail_with_events(r#type).await?;
+            for err in &mail.errors {
+                eprintln!("Skipping {err}");
+            }
+            warn_if_cached(&client);
+            let calendar = ics::calendar(&mail.letters, Utc::now());
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, calendar)
+                        .with_context(|| format!("Couldn't write {}", path.display()))?;
+                    println!("Saved the calendar to {}", path.display());
                 }
-            } else {
-                eprintln!(
-                    "You don't have an api key! Run orphy setup [your api key] with your api key."
-                )
+                None => print!("{calendar}"),
+            }
+        }
+        Command::Export {
+            command: ExportCommand::Feed { output, r#type },
+        } => {
+            let feed_format = match (feed_format, format) {
+                (Some(feed_format), _) => feed_format,
+                (None, Format::Table) => FeedFormat::Atom,
+                (None, _) => bail!("orphy export feed writes --format atom or --format rss"),
+            };
+            let (_, client) = connect(&cfg, profile, &network)?;
+            let mail = client.get_mail_with_events(r#type).await?;
+            for err in &mail.errors {
+                eprintln!("Skipping {err}");
+            }
+            warn_if_cached(&client);
+            let feed = match feed_format {
+                FeedFormat::Atom => feed::atom(&mail.letters, client.base(), Utc::now()),
+                FeedFormat::Rss => feed::rss(&mail.letters, client.base(), Utc::now()),
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, feed)
+                        .with_context(|| format!("Couldn't write {}", path.display()))?;
+                    println!("Saved the feed to {}", path.display());
+                }
+                None => print!("{feed}"),
+            }
+        }
+        Command::Changes => {
+            // cached mail would be recorded as this sync and hide what changed since
+            let network = Network {
+                stale_fallback: false,
+                ..network
+            };
+            let (name, client) = connect(&cfg, profile, &network)?;
+            let mut store = Store::open(&history_path(&name)?)?;
+            let since = store.last_sync()?;
+            if format == Format::Table {
+                println!("Checking your mail for changes...");
+            }
+            let mail = client.get_mail_with_events(None).await?;
+            for err in &mail.errors {
+                eprintln!("Skipping {err}");
             }
+            warn_if_cached(&client);
+            let changes = store.sync(&mail.letters)?;
+            output::print_changes(format, &changes, since)?;
+            if let Ok(cfg) = &cfg {
+                deliver(&cfg.webhooks, &changes).await;
+            }
+        }
+        Command::Watch {
+            id,
+            r#type,
+            interval,
+            exec,
+            append,
+            bell,
+        } => {
+            let network = Network {
+                stale_fallback: false,
+                ..network
+            };
+            let (_, client) = connect(&cfg, profile, &network)?;
+            let mut notifiers = cfg
+                .as_ref()
+                .map(|cfg| cfg.notify.clone())
+                .unwrap_or_default();
+            let flags = Notifier {
+                command: exec,
+                file: append,
+                bell,
+            };
+            if !flags.is_empty() {
+                notifiers.push(flags);
+            }
+            let mut watcher = match id {
+                Some(id) => Watcher::one(id, r#type),
+                None => Watcher::all(),
+            };
+            let mut stream = ChangeStream::new(format);
+            let webhooks = cfg
+                .as_ref()
+                .map(|cfg| cfg.webhooks.clone())
+                .unwrap_or_default();
+            watch(&client, &mut watcher, interval, async |changes| {
+                stream.print(changes)?;
+                for notifier in &notifiers {
+                    if let Err(err) = notifier.notify(changes).await {
+                        eprintln!("{err}");
+                    }
+                }
+                deliver(&webhooks, changes).await;
+                Ok(())
+            })
+            .await?;
+        }
+        Command::Serve { bind, interval } => {
+            // they fetch every interval themselves, an answer from the cache wouldn't be news
+            let network = Network {
+                stale_fallback: false,
+                max_age: None,
+                ..network
+            };
+            let (_, client) = connect(&cfg, profile, &network)?;
+            serve::run(&client, bind, interval).await?;
+        }
+        Command::Exporter { bind, interval } => {
+            // they fetch every interval themselves, an answer from the cache wouldn't be news
+            let network = Network {
+                stale_fallback: false,
+                max_age: None,
+                ..network
+            };
+            let (_, client) = connect(&cfg, profile, &network)?;
+            serve::export(&client, bind, interval).await?;
+        }
+        Command::Tui => {
+            let (_, client) = connect(&cfg, profile, &network)?;
+            tui::run(client).await?;
         }
-        Cli::Credit => {
+        Command::Credit => {
             println!(
                 "
 ███████╗██████╗ ██████╗  ██████╗ ██████╗  ██████╗ ██████╗ ██████╗ ███████╗ ██████╗ 
@@ -313,7 +744,7 @@ async fn main() -> Result<(), Error> {
             println!("Created with <3 by ErrorCode0");
             println!("@errorcodezero on github")
         }
-        Cli::Fun => {
+        Command::Fun => {
             let animation_frames: Vec<String> = vec![
                 String::from(
                     "
@@ -0,0 +1,174 @@
+use crate::error::MailError;
+use crate::mail::Letter;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Upper bounds, in seconds, of the buckets request latencies are counted in.
+pub const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+/// How the requests a [`MailClient`](crate::MailClient) sent to Hack Club Mail went. Every try
+/// counts, retries included, while answers that came from the cache don't.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RequestMetrics {
+    pub requests: u64,
+    /// How many requests took at most each of [`LATENCY_BUCKETS`].
+    pub buckets: [u64; LATENCY_BUCKETS.len()],
+    /// How long all the requests took together.
+    pub latency: Duration,
+    /// How many requests failed, by [`MailError::kind`].
+    pub errors: BTreeMap<&'static str, u64>,
+}
+
+impl RequestMetrics {
+    /// Counts a request that took `latency` and failed with `error`, if it did.
+    pub fn record(&mut self, latency: Duration, error: Option<&MailError>) {
+        self.requests += 1;
+        self.latency += latency;
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if latency.as_secs_f64() <= bound {
+                *bucket += 1;
+            }
+        }
+        if let Some(error) = error {
+            *self.errors.entry(error.kind()).or_default() += 1;
+        }
+    }
+}
+
+/// Metrics about `letters` and `requests` in the Prometheus text format. Mail needs its events
+/// for the last event timestamp, see
+/// [`MailClient::get_mail_with_events`](crate::MailClient::get_mail_with_events).
+pub fn prometheus(letters: &[Letter], requests: &RequestMetrics, now: DateTime<Utc>) -> String {
+    let mut out = String::new();
+
+    let mut counts: BTreeMap<(&str, &str), usize> = BTreeMap::new();
+    for letter in letters {
+        let letter_type = letter.letter_type.as_deref().unwrap_or("unknown");
+        let status = letter.status.as_deref().unwrap_or("unknown");
+        *counts.entry((letter_type, status)).or_default() += 1;
+    }
+    header(
+        &mut out,
+        "orphy_mail",
+        "gauge",
+        "Pieces of mail by type and status.",
+    );
+    for ((letter_type, status), count) in counts {
+        out += &format!(
+            "orphy_mail{{type=\"{}\",status=\"{}\"}} {count}\n",
+            label(letter_type),
+            label(status)
+        );
+    }
+
+    let oldest = letters
+        .iter()
+        .filter(|letter| !letter.is_delivered())
+        .filter_map(|letter| letter.created_at)
+        .min();
+    header(
+        &mut out,
+        "orphy_oldest_undelivered_age_seconds",
+        "gauge",
+        "How long ago the oldest mail that hasn't been delivered was created, 0 when there's none.",
+    );
+    let age = oldest.map_or(0, |oldest| (now - oldest).num_seconds().max(0));
+    out += &format!("orphy_oldest_undelivered_age_seconds {age}\n");
+
+    let last_event = letters
+        .iter()
+        .flat_map(|letter| letter.events.iter().flatten())
+        .filter_map(|event| event.happened_at)
+        .max();
+    header(
+        &mut out,
+        "orphy_last_event_timestamp_seconds",
+        "gauge",
+        "When the most recent tracking event happened, as a unix timestamp.",
+    );
+    if let Some(last_event) = last_event {
+        out += &format!(
+            "orphy_last_event_timestamp_seconds {}\n",
+            last_event.timestamp()
+        );
+    }
+
+    header(
+        &mut out,
+        "orphy_api_requests_total",
+        "counter",
+        "Requests sent to Hack Club Mail, retries included.",
+    );
+    out += &format!("orphy_api_requests_total {}\n", requests.requests);
+
+    header(
+        &mut out,
+        "orphy_api_request_errors_total",
+        "counter",
+        "Requests to Hack Club Mail that failed, by what went wrong.",
+    );
+    for (kind, count) in &requests.errors {
+        out += &format!("orphy_api_request_errors_total{{kind=\"{kind}\"}} {count}\n");
+    }
+
+    header(
+        &mut out,
+        "orphy_api_request_duration_seconds",
+        "histogram",
+        "How long requests to Hack Club Mail took.",
+    );
+    for (bound, count) in LATENCY_BUCKETS.iter().zip(requests.buckets) {
+        out += &format!("orphy_api_request_duration_seconds_bucket{{le=\"{bound}\"}} {count}\n");
+    }
+    out += &format!(
+        "orphy_api_request_duration_seconds_bucket{{le=\"+Inf\"}} {}\n",
+        requests.requests
+    );
+    out += &format!(
+        "orphy_api_request_duration_seconds_sum {}\n",
+        requests.latency.as_secs_f64()
+    );
+    out += &format!(
+        "orphy_api_request_duration_seconds_count {}\n",
+        requests.requests
+    );
+    out
+}
+
+/// Metrics about how something that fetches the mail every so often, like `orphy exporter`, is
+/// keeping up: when the mail it has was fetched, and how many times fetching it again failed.
+pub fn refreshes(refreshed_at: DateTime<Utc>, errors: u64) -> String {
+    let mut out = String::new();
+    header(
+        &mut out,
+        "orphy_last_refresh_timestamp_seconds",
+        "gauge",
+        "When the mail was last fetched from Hack Club Mail, as a unix timestamp.",
+    );
+    out += &format!(
+        "orphy_last_refresh_timestamp_seconds {}\n",
+        refreshed_at.timestamp()
+    );
+
+    header(
+        &mut out,
+        "orphy_refresh_errors_total",
+        "counter",
+        "Times fetching the mail again failed, keeping the mail from before.",
+    );
+    out += &format!("orphy_refresh_errors_total {errors}\n");
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    *out += &format!("# HELP {name} {help}\n");
+    *out += &format!("# TYPE {name} {kind}\n");
+}
+
+fn label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,141 @@
+use crate::changes::Change;
+use crate::error::NotifyError;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::process::Command;
+
+/// Things to do when mail changes. Every action that's set runs for every change, in the order
+/// they're listed here.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Notifier {
+    /// A shell command run once per change. It's told about the change through environment
+    /// variables rather than by pasting text into the command, so nothing needs quoting:
+    ///
+    /// - `ORPHY_KIND`: `new_letter`, `status_changed` or `new_event`
+    /// - `ORPHY_ID`, `ORPHY_TITLE` and `ORPHY_STATUS` of the mail
+    /// - `ORPHY_DESCRIPTION`: the change in a few words, see [`Change::describe`]
+    /// - `ORPHY_JSON`: the whole change as json
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// A file or named pipe that gets a line of json for every change, like
+    /// `orphy watch --format ndjson`. Files are created if they don't exist. A pipe nothing is
+    /// reading from is an error rather than something to wait on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<PathBuf>,
+    /// Rings the terminal bell and prints a summary on stderr.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bell: bool,
+}
+
+impl Notifier {
+    /// Whether none of the actions are set.
+    pub fn is_empty(&self) -> bool {
+        self.command.is_none() && self.file.is_none() && !self.bell
+    }
+
+    /// Runs every action for `changes`. One failing doesn't stop the rest, the first error is
+    /// returned once they've all had a go. Commands are killed if this is dropped before they
+    /// finish.
+    pub async fn notify(&self, changes: &[Change]) -> Result<(), NotifyError> {
+        if changes.is_empty() {
+            return Ok(());
+        }
+        let mut result = Ok(());
+        if let Some(command) = &self.command {
+            for change in changes {
+                result = result.and(run(command, change).await);
+            }
+        }
+        if let Some(path) = &self.file {
+            result = result.and(write(path, changes));
+        }
+        if self.bell {
+            eprintln!("\x07{}", summary(changes));
+        }
+        result
+    }
+}
+
+/// One line about a batch of changes, for the bell.
+pub fn summary(changes: &[Change]) -> String {
+    match changes {
+        [change] => {
+            let letter = change.letter();
+            format!(
+                "{} {}: {}",
+                letter.id.as_deref().unwrap_or("no id"),
+                letter.title.as_deref().unwrap_or("no name"),
+                change.describe()
+            )
+        }
+        changes => format!("{} changes to your mail", changes.len()),
+    }
+}
+
+async fn run(command: &str, change: &Change) -> Result<(), NotifyError> {
+    let letter = change.letter();
+    let mut process = if cfg!(windows) {
+        let mut process = Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    } else {
+        let mut process = Command::new("sh");
+        process.args(["-c", command]);
+        process
+    };
+    let status = process
+        .stdin(Stdio::null())
+        .env("ORPHY_KIND", change.kind())
+        .env("ORPHY_ID", letter.id.as_deref().unwrap_or_default())
+        .env("ORPHY_TITLE", letter.title.as_deref().unwrap_or_default())
+        .env("ORPHY_STATUS", letter.status.as_deref().unwrap_or_default())
+        .env("ORPHY_DESCRIPTION", change.describe())
+        .env(
+            "ORPHY_JSON",
+            serde_json::to_string(change).unwrap_or_default(),
+        )
+        .kill_on_drop(true)
+        .status()
+        .await
+        .map_err(|source| NotifyError::Spawn {
+            command: command.to_string(),
+            source,
+        })?;
+    if !status.success() {
+        return Err(NotifyError::Command {
+            command: command.to_string(),
+            status,
+        });
+    }
+    Ok(())
+}
+
+fn write(path: &Path, changes: &[Change]) -> Result<(), NotifyError> {
+    let failed = |source| NotifyError::Write {
+        path: path.to_path_buf(),
+        source,
+    };
+    // one write for the whole batch, so readers of a pipe don't see half of it
+    let mut lines = Vec::new();
+    for change in changes {
+        serde_json::to_writer(&mut lines, change).map_err(|err| failed(err.into()))?;
+        lines.push(b'\n');
+    }
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    // opening a pipe for writing waits for a reader unless it's non-blocking
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::custom_flags(&mut options, libc::O_NONBLOCK);
+    let mut file = options.open(path).map_err(|err| match err.raw_os_error() {
+        #[cfg(unix)]
+        Some(libc::ENXIO) => NotifyError::NoReader {
+            path: path.to_path_buf(),
+        },
+        _ => failed(err),
+    })?;
+    file.write_all(&lines).map_err(failed)
+}
@@ -0,0 +1,1005 @@
+use crate::cli::Format;
+use anyhow::Error;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use cli_table::{Cell, CellStruct, Style, Table};
+use orphy::stats::{KindStats, Transit};
+use orphy::{Carrier, Change, Event, Letter, Stats};
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::{Write, stdout};
+use std::time::Duration;
+
+/// What `orphy fetch` reports about an account.
+#[derive(Serialize)]
+pub struct Summary {
+    pub profile: String,
+    pub id: Option<String>,
+    pub instance: String,
+    pub last_mailed: Option<DateTime<Utc>>,
+    pub mail: usize,
+    pub letters: usize,
+    pub packages: usize,
+    pub legacy: usize,
+}
+
+// csv can't nest, so letters are flattened into one row each (and one row per event for view)
+#[derive(Serialize)]
+struct LetterRow<'a> {
+    id: Option<&'a str>,
+    title: Option<&'a str>,
+    #[serde(rename = "type")]
+    letter_type: Option<&'a str>,
+    #[serde(rename = "subtype")]
+    letter_subtype: Option<&'a str>,
+    status: Option<&'a str>,
+    tags: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    public_url: Option<&'a str>,
+    tracking_number: Option<&'a str>,
+    tracking_link: Option<&'a str>,
+    carrier: Option<Carrier>,
+    tracking_url: Option<String>,
+    path: Option<&'a str>,
+}
+
+// csv doesn't support #[serde(flatten)] so the letter columns are repeated here
+#[derive(Serialize)]
+struct EventRow<'a> {
+    id: Option<&'a str>,
+    title: Option<&'a str>,
+    #[serde(rename = "type")]
+    letter_type: Option<&'a str>,
+    #[serde(rename = "subtype")]
+    letter_subtype: Option<&'a str>,
+    status: Option<&'a str>,
+    tags: Option<String>,
+    created_at: Option<String>,
+    updated_at: Option<String>,
+    public_url: Option<&'a str>,
+    tracking_number: Option<&'a str>,
+    tracking_link: Option<&'a str>,
+    carrier: Option<Carrier>,
+    tracking_url: Option<String>,
+    path: Option<&'a str>,
+    event_happened_at: Option<String>,
+    event_source: Option<&'a str>,
+    event_facility: Option<&'a str>,
+    event_description: Option<&'a str>,
+    event_location: Option<&'a str>,
+}
+
+impl<'a> From<&'a Letter> for LetterRow<'a> {
+    fn from(letter: &'a Letter) -> Self {
+        Self {
+            id: letter.id.as_deref(),
+            title: letter.title.as_deref(),
+            letter_type: letter.letter_type.as_deref(),
+            letter_subtype: letter.letter_subtype.as_deref(),
+            status: letter.status.as_deref(),
+            tags: letter.tags.as_ref().map(|tags| tags.join(";")),
+            created_at: letter.created_at.map(|date| date.to_rfc3339()),
+            updated_at: letter.updated_at.map(|date| date.to_rfc3339()),
+            public_url: letter.public_url.as_deref(),
+            tracking_number: letter.tracking_number.as_deref(),
+            tracking_link: letter.tracking_link.as_deref(),
+            carrier: letter.carrier(),
+            tracking_url: letter.tracking_url(),
+            path: letter.path.as_deref(),
+        }
+    }
+}
+
+impl<'a> EventRow<'a> {
+    fn new(letter: &'a Letter, event: Option<&'a Event>) -> Self {
+        let row = LetterRow::from(letter);
+        Self {
+            id: row.id,
+            title: row.title,
+            letter_type: row.letter_type,
+            letter_subtype: row.letter_subtype,
+            status: row.status,
+            tags: row.tags,
+            created_at: row.created_at,
+            updated_at: row.updated_at,
+            public_url: row.public_url,
+            tracking_number: row.tracking_number,
+            tracking_link: row.tracking_link,
+            carrier: row.carrier,
+            tracking_url: row.tracking_url,
+            path: row.path,
+            event_happened_at: event
+                .and_then(|event| event.happened_at)
+                .map(|date| date.to_rfc3339()),
+            event_source: event.and_then(|event| event.source.as_deref()),
+            event_facility: event.and_then(|event| event.facility.as_deref()),
+            event_description: event.and_then(|event| event.description.as_deref()),
+            event_location: event.and_then(|event| event.location.as_deref()),
+        }
+    }
+}
+
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<(), Error> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn print_ndjson<'a, T: Serialize + 'a>(
+    values: impl IntoIterator<Item = &'a T>,
+) -> Result<(), Error> {
+    let mut out = stdout().lock();
+    for value in values {
+        serde_json::to_writer(&mut out, value)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn print_yaml<T: Serialize + ?Sized>(value: &T) -> Result<(), Error> {
+    print!("{}", serde_yaml::to_string(value)?);
+    Ok(())
+}
+
+fn print_csv<T: Serialize>(rows: impl IntoIterator<Item = T>) -> Result<(), Error> {
+    let mut writer = csv::Writer::from_writer(stdout().lock());
+    for row in rows {
+        writer.serialize(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+pub fn letter_name(letter: &Letter) -> String {
+    if let Some(title) = &letter.title {
+        title.clone()
+    } else if let Some(tags) = &letter.tags {
+        format!("letter: {}", tags.join(", "))
+    } else {
+        String::from("no name")
+    }
+}
+
+/// Prints the list from `orphy mail`.
+pub fn print_letters(format: Format, letters: Vec<Letter>) -> Result<(), Error> {
+    match format {
+        Format::Json => print_json(&letters.iter().map(LetterView::new).collect::<Vec<_>>()),
+        Format::Ndjson => print_ndjson(&letters.iter().map(LetterView::new).collect::<Vec<_>>()),
+        Format::Yaml => print_yaml(&letters.iter().map(LetterView::new).collect::<Vec<_>>()),
+        Format::Csv => print_csv(letters.iter().map(LetterRow::from)),
+        Format::Table => {
+            if letters.is_empty() {
+                println!("You don't have any mail!");
+                return Ok(());
+            }
+            let mut table: Vec<Vec<CellStruct>> = Vec::new();
+            for letter in letters {
+                let mut row: Vec<CellStruct> = Vec::new();
+                row.push(letter_name(&letter).cell());
+                if let Some(letter_type) = letter.letter_type {
+                    row.push(letter_type.cell())
+                } else {
+                    row.push(String::from("no type").cell())
+                }
+                if let Some(id) = letter.id {
+                    row.push(id.cell())
+                } else {
+                    row.push(String::from("no id").cell())
+                }
+                if let Some(status) = letter.status {
+                    row.push(status.cell());
+                } else {
+                    row.push(String::from("no status").cell())
+                }
+                if let Some(created_at) = letter.created_at {
+                    row.push(created_at.date_naive().to_string().cell());
+                } else {
+                    row.push(String::from("no date").cell())
+                }
+                table.push(row);
+            }
+            let table = table
+                .table()
+                .title(vec!["Name", "Type", "ID", "Status", "Creation Date"])
+                .display()?;
+
+            println!("{}", table);
+            println!(
+                "View more details by using orphy view --id (put the id of your letter here!)"
+            );
+            Ok(())
+        }
+    }
+}
+
+/// How much of each piece of mail `orphy view` shows.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ViewMode {
+    /// The details and the whole timeline.
+    #[default]
+    Full,
+    /// Just the timeline.
+    EventsOnly,
+    /// Just the most recent event.
+    LatestEvent,
+}
+
+/// The fields [`LetterView`] adds to a letter.
+const VIEW_FIELDS: [&str; 2] = ["carrier", "tracking_url"];
+
+/// A letter with the carrier and tracking page orphy worked out next to what the api gave.
+#[derive(Serialize)]
+struct LetterView<'a> {
+    #[serde(flatten)]
+    letter: Cow<'a, Letter>,
+    carrier: Option<Carrier>,
+    tracking_url: Option<String>,
+}
+
+#[derive(Serialize)]
+struct EventsView<'a> {
+    id: Option<&'a str>,
+    title: Option<&'a str>,
+    events: Vec<&'a Event>,
+}
+
+#[derive(Serialize)]
+struct LatestEventView<'a> {
+    id: Option<&'a str>,
+    title: Option<&'a str>,
+    status: Option<&'a str>,
+    latest_event: Option<&'a Event>,
+}
+
+impl<'a> LetterView<'a> {
+    fn new(letter: &'a Letter) -> Self {
+        let carrier = letter.carrier();
+        let tracking_url = letter.tracking_url();
+        // fields of the same name from the api would be written twice
+        let letter = if VIEW_FIELDS
+            .iter()
+            .any(|key| letter.extra.contains_key(*key))
+        {
+            let mut letter = letter.clone();
+            letter
+                .extra
+                .retain(|key, _| !VIEW_FIELDS.contains(&key.as_str()));
+            Cow::Owned(letter)
+        } else {
+            Cow::Borrowed(letter)
+        };
+        Self {
+            letter,
+            carrier,
+            tracking_url,
+        }
+    }
+}
+
+impl<'a> EventsView<'a> {
+    fn new(letter: &'a Letter) -> Self {
+        Self {
+            id: letter.id.as_deref(),
+            title: letter.title.as_deref(),
+            events: letter.timeline(),
+        }
+    }
+}
+
+impl<'a> LatestEventView<'a> {
+    fn new(letter: &'a Letter) -> Self {
+        Self {
+            id: letter.id.as_deref(),
+            title: letter.title.as_deref(),
+            status: letter.status.as_deref(),
+            latest_event: letter.latest_event(),
+        }
+    }
+}
+
+/// Prints a single letter and its events for `orphy view`.
+pub fn print_letter(format: Format, letter: Letter, mode: ViewMode) -> Result<(), Error> {
+    match (format, mode) {
+        (Format::Json, ViewMode::Full) => print_json(&LetterView::new(&letter)),
+        (Format::Json, ViewMode::EventsOnly) => print_json(&EventsView::new(&letter)),
+        (Format::Json, ViewMode::LatestEvent) => print_json(&LatestEventView::new(&letter)),
+        (Format::Yaml, ViewMode::Full) => print_yaml(&LetterView::new(&letter)),
+        (Format::Yaml, ViewMode::EventsOnly) => print_yaml(&EventsView::new(&letter)),
+        (Format::Yaml, ViewMode::LatestEvent) => print_yaml(&LatestEventView::new(&letter)),
+        (Format::Ndjson | Format::Csv, _) => {
+            print_report(format, std::slice::from_ref(&letter), mode)
+        }
+        (Format::Table, ViewMode::Full) => {
+            let mut table: Vec<Vec<CellStruct>> = Vec::new();
+            if let Some(id) = &letter.id {
+                table.push(vec!["ID".cell(), id.cell()]);
+            } else {
+                table.push(vec!["ID".cell(), "no id".cell()]);
+            }
+            table.push(vec!["Name".cell(), letter_name(&letter).cell()]);
+            if let Some(letter_type) = &letter.letter_type {
+                table.push(vec!["Type".cell(), letter_type.cell()]);
+            } else {
+                table.push(vec!["Type".cell(), "no type".cell()]);
+            }
+            if let Some(letter_subtype) = &letter.letter_subtype {
+                table.push(vec!["Subtype".cell(), letter_subtype.cell()]);
+            }
+            if let Some(status) = &letter.status {
+                table.push(vec!["Status".cell(), status.cell()]);
+            } else {
+                table.push(vec!["Status".cell(), "no status".cell()]);
+            }
+            if let Some(created_at) = letter.created_at {
+                table.push(vec!["Created At".cell(), created_at.cell()]);
+            } else {
+                table.push(vec!["Created At".cell(), "no creation date".cell()]);
+            }
+            if let Some(updated_at) = letter.updated_at {
+                table.push(vec!["Updated At".cell(), updated_at.cell()]);
+            }
+            if let Some(public_url) = &letter.public_url {
+                table.push(vec!["Public URL".cell(), public_url.cell()]);
+            }
+            if let Some(tracking_number) = &letter.tracking_number {
+                table.push(vec!["Tracking Number".cell(), tracking_number.cell()]);
+            }
+            if let Some(carrier) = letter.carrier() {
+                table.push(vec!["Carrier".cell(), carrier.cell()]);
+            }
+            if let Some(tracking_url) = letter.tracking_url() {
+                table.push(vec!["Tracking Link".cell(), tracking_url.cell()]);
+            }
+
+            let table = table.table().display()?;
+            println!("{}", table);
+
+            let events = letter.timeline();
+            if !events.is_empty() {
+                println!("Events");
+                print_timeline(&events, Utc::now());
+            }
+            Ok(())
+        }
+        (Format::Table, ViewMode::EventsOnly) => {
+            println!(
+                "{} ({})",
+                letter_name(&letter),
+                letter.id.as_deref().unwrap_or("no id")
+            );
+            let events = letter.timeline();
+            if events.is_empty() {
+                println!("No events yet");
+            } else {
+                print_timeline(&events, Utc::now());
+            }
+            Ok(())
+        }
+        (Format::Table, ViewMode::LatestEvent) => {
+            println!("{}", describe_latest_event(&letter, Utc::now()));
+            Ok(())
+        }
+    }
+}
+
+/// Prints the details and events of many pieces of mail for `orphy view` with more than one id.
+pub fn print_report(format: Format, letters: &[Letter], mode: ViewMode) -> Result<(), Error> {
+    match (format, mode) {
+        (Format::Json, ViewMode::Full) => {
+            print_json(&letters.iter().map(LetterView::new).collect::<Vec<_>>())
+        }
+        (Format::Ndjson, ViewMode::Full) => {
+            print_ndjson(&letters.iter().map(LetterView::new).collect::<Vec<_>>())
+        }
+        (Format::Yaml, ViewMode::Full) => {
+            print_yaml(&letters.iter().map(LetterView::new).collect::<Vec<_>>())
+        }
+        (Format::Json, ViewMode::EventsOnly) => {
+            print_json(&letters.iter().map(EventsView::new).collect::<Vec<_>>())
+        }
+        (Format::Ndjson, ViewMode::EventsOnly) => {
+            print_ndjson(&letters.iter().map(EventsView::new).collect::<Vec<_>>())
+        }
+        (Format::Yaml, ViewMode::EventsOnly) => {
+            print_yaml(&letters.iter().map(EventsView::new).collect::<Vec<_>>())
+        }
+        (Format::Json, ViewMode::LatestEvent) => {
+            print_json(&letters.iter().map(LatestEventView::new).collect::<Vec<_>>())
+        }
+        (Format::Ndjson, ViewMode::LatestEvent) => {
+            print_ndjson(&letters.iter().map(LatestEventView::new).collect::<Vec<_>>())
+        }
+        (Format::Yaml, ViewMode::LatestEvent) => {
+            print_yaml(&letters.iter().map(LatestEventView::new).collect::<Vec<_>>())
+        }
+        (Format::Csv, ViewMode::LatestEvent) => print_csv(
+            letters
+                .iter()
+                .map(|letter| EventRow::new(letter, letter.latest_event())),
+        ),
+        (Format::Csv, _) => print_csv(letters.iter().flat_map(|letter| {
+            let events = letter.timeline();
+            if events.is_empty() {
+                vec![EventRow::new(letter, None)]
+            } else {
+                events
+                    .into_iter()
+                    .map(|event| EventRow::new(letter, Some(event)))
+                    .collect()
+            }
+        })),
+        (Format::Table, _) => {
+            if letters.is_empty() {
+                println!("You don't have any mail!");
+                return Ok(());
+            }
+            for (index, letter) in letters.iter().enumerate() {
+                if index > 0 && mode != ViewMode::LatestEvent {
+                    println!();
+                }
+                print_letter(Format::Table, letter.clone(), mode)?;
+            }
+            println!();
+            println!("{} pieces of mail", letters.len());
+            Ok(())
+        }
+    }
+}
+
+/// Prints events as a vertical timeline, with how long ago each one happened and how long it
+/// took to get from one scan to the next.
+fn print_timeline(events: &[&Event], now: DateTime<Utc>) {
+    for (index, event) in events.iter().enumerate() {
+        let last = index + 1 == events.len();
+        let rail = if last { " " } else { "│" };
+
+        println!(
+            "● {}",
+            event.description.as_deref().unwrap_or("no description")
+        );
+        match event.happened_at {
+            Some(happened_at) => println!(
+                "{rail} {} ({})",
+                happened_at.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+                relative_time(happened_at, now)
+            ),
+            None => println!("{rail} unknown time"),
+        }
+        if let Some(place) = event.place(" · ") {
+            println!("{rail} {place}");
+        }
+
+        if let Some(next) = events.get(index + 1) {
+            match (event.happened_at, next.happened_at) {
+                (Some(from), Some(to)) => println!("│   {} later", rough_duration(to - from)),
+                _ => println!("│"),
+            }
+        }
+    }
+}
+
+/// One line about the most recent event of a piece of mail, for `orphy view --latest-event`.
+fn describe_latest_event(letter: &Letter, now: DateTime<Utc>) -> String {
+    let mut line = format!(
+        "{} {}: ",
+        letter.id.as_deref().unwrap_or("no id"),
+        letter_name(letter)
+    );
+    let Some(event) = letter.latest_event() else {
+        line += "no events yet";
+        return line;
+    };
+    line += event.description.as_deref().unwrap_or("new event");
+    if let Some(place) = event.facility.as_ref().or(event.location.as_ref()) {
+        line += &format!(" at {place}");
+    }
+    if let Some(happened_at) = event.happened_at {
+        line += &format!(" ({})", relative_time(happened_at, now));
+    }
+    line
+}
+
+/// "3 days ago", or "in 2 hours" for the odd timestamp from the future.
+fn relative_time(date: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let duration = now - date;
+    if duration.num_seconds().abs() < 60 {
+        String::from("just now")
+    } else if duration < TimeDelta::zero() {
+        format!("in {}", rough_duration(-duration))
+    } else {
+        format!("{} ago", rough_duration(duration))
+    }
+}
+
+/// A duration in its two largest units, e.g. "2 days 5 hours" or "40 minutes". Past a week the
+/// hours are left out.
+fn rough_duration(duration: TimeDelta) -> String {
+    fn unit(count: i64, name: &str) -> String {
+        if count == 1 {
+            format!("1 {name}")
+        } else {
+            format!("{count} {name}s")
+        }
+    }
+
+    let seconds = duration.num_seconds().abs();
+    let (days, hours, minutes) = (seconds / 86400, seconds % 86400 / 3600, seconds % 3600 / 60);
+    if days >= 7 || (days > 0 && hours == 0) {
+        unit(days, "day")
+    } else if days > 0 {
+        format!("{} {}", unit(days, "day"), unit(hours, "hour"))
+    } else if hours > 0 && minutes > 0 {
+        format!("{} {}", unit(hours, "hour"), unit(minutes, "minute"))
+    } else if hours > 0 {
+        unit(hours, "hour")
+    } else if minutes > 0 {
+        unit(minutes, "minute")
+    } else {
+        String::from("less than a minute")
+    }
+}
+
+/// Prints the `orphy fetch` overview.
+pub fn print_summary(format: Format, summary: &Summary) -> Result<(), Error> {
+    match format {
+        Format::Json => print_json(summary),
+        Format::Ndjson => print_ndjson([summary]),
+        Format::Yaml => print_yaml(summary),
+        Format::Csv => print_csv([summary]),
+        Format::Table => {
+            println!(
+                "
+⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⣠⡤⠶⠒⠛⠉⠙⠛⠒⠶⢤⣀⠀⠀⠀⠀⠀⠀
+⠀⠀⠀⠀⠀⠀⠀⠀⠀⢀⡴⠋⠁⠀⠀⠀⠀⠀⠀⠁⠀⠀⠀⠈⠙⢦⡀⠀⠀⠀ {}
+⠀⠀⠀⠀⣀⣠⠤⠤⡴⠻⢓⣶⠦⠤⣄⡀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠙⢦⡀⠀ instance: {}
+⣀⡠⠴⠊⠁⠀⠀⠀⠀⠀⠒⠽⠀⠀⠀⠉⢙⠒⢢⡄⠀⠀⠀⠀⠀⠀⠀⠈⢷⡀ {}
+⠘⢆⠉⠑⡆⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⢆⠁⣠⠎⠀⠀⠀⠀⠀⠀⠀⠀⠀⠈⣇ mail: {}
+⠀⠈⠓⠦⠁⢀⣀⠀⠀⠀⠀⠀⠀⣀⣀⢸⡊⠁⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⠀⢻ letters: {}
+⠀⠀⠀⡸⠀⠉⠀⠙⠆⠀⠀⠀⠏⠀⠈⠀⢇⠀⠀⠀⠀⠀⠀⠀⢀⠔⠀⠀⠀⣼ packages: {}
+⠀⠀⠀⠹⣄⠀⠠⣤⡡⠪⡭⠃⡤⢤⡄⡰⠋⠀⠀⠀⣀⡠⠴⠊⠁⠀⠀⠀⢠⠇ legacy: {}
+⠀⠀⢠⢿⣠⠟⠓⠛⠉⠛⡟⠛⠛⠛⠛⠒⠒⠚⠉⠉⠁⠀⠀⠀⠀⠀⢀⡴⠋⠀
+⠀⠀⠈⠛⢯⣀⣀⣀⡤⠤⠤⠤⢤⣤⣀⣀⣀⣀⣀⣀⣀⣤⠤⠴⠒⠋⠁⠀⠀⠀ 
+",
+                if let Some(id) = &summary.id {
+                    format!("id: {id}")
+                } else {
+                    String::from("")
+                },
+                summary.instance,
+                if let Some(last_mailed) = summary.last_mailed {
+                    format!("last mailed: {}", last_mailed.naive_utc())
+                } else {
+                    String::from("")
+                },
+                summary.mail,
+                summary.letters,
+                summary.packages,
+                summary.legacy
+            );
+            Ok(())
+        }
+    }
+}
+
+// csv can't nest either, so each number of `orphy stats` is a row of its own
+#[derive(Serialize)]
+struct StatRow<'a> {
+    section: &'static str,
+    name: Cow<'a, str>,
+    mail: usize,
+    average_seconds: Option<u64>,
+}
+
+fn kind_name(kind: &KindStats) -> String {
+    match (&kind.letter_type, &kind.letter_subtype) {
+        (Some(letter_type), Some(letter_subtype)) => format!("{letter_type}/{letter_subtype}"),
+        (Some(letter_type), None) => letter_type.clone(),
+        (None, _) => String::from("no type"),
+    }
+}
+
+fn duration_cell(duration: Duration) -> CellStruct {
+    TimeDelta::from_std(duration)
+        .map_or_else(|_| String::from("forever"), rough_duration)
+        .cell()
+}
+
+/// Prints what `orphy stats` worked out.
+pub fn print_stats(format: Format, stats: &Stats) -> Result<(), Error> {
+    match format {
+        Format::Json => print_json(stats),
+        Format::Ndjson => print_ndjson([stats]),
+        Format::Yaml => print_yaml(stats),
+        Format::Csv => {
+            let transit = |name: &'static str, transit: &Option<Transit>| StatRow {
+                section: "transit",
+                name: name.into(),
+                mail: transit.map_or(0, |transit| transit.mail),
+                average_seconds: transit.map(|transit| transit.average.as_secs()),
+            };
+            let rows = [
+                transit("to_first_event", &stats.to_first_event),
+                transit("to_delivery", &stats.to_delivery),
+            ]
+            .into_iter()
+            .chain(stats.kinds.iter().map(|kind| StatRow {
+                section: "kind",
+                name: kind_name(kind).into(),
+                mail: kind.mail,
+                average_seconds: kind.to_delivery.map(|transit| transit.average.as_secs()),
+            }))
+            .chain(stats.statuses.iter().map(|(status, mail)| StatRow {
+                section: "status",
+                name: status.into(),
+                mail: *mail,
+                average_seconds: None,
+            }))
+            .chain(stats.facilities.iter().map(|facility| StatRow {
+                section: "facility",
+                name: facility.facility.as_str().into(),
+                mail: facility.events,
+                average_seconds: None,
+            }))
+            .chain(stats.months.iter().map(|(month, mail)| StatRow {
+                section: "month",
+                name: month.into(),
+                mail: *mail,
+                average_seconds: None,
+            }));
+            print_csv(rows)
+        }
+        Format::Table => {
+            if stats.mail == 0 {
+                println!("You don't have any mail!");
+                return Ok(());
+            }
+            println!("Stats for {} pieces of mail", stats.mail);
+
+            let transit: Vec<Vec<CellStruct>> = [
+                ("Created to first scan", &stats.to_first_event),
+                ("Created to delivery", &stats.to_delivery),
+            ]
+            .into_iter()
+            .map(|(name, transit)| match transit {
+                Some(transit) => vec![
+                    name.cell(),
+                    transit.mail.cell(),
+                    duration_cell(transit.average),
+                    duration_cell(transit.median),
+                    duration_cell(transit.fastest),
+                    duration_cell(transit.slowest),
+                ],
+                None => vec![
+                    name.cell(),
+                    0.cell(),
+                    "".cell(),
+                    "".cell(),
+                    "".cell(),
+                    "".cell(),
+                ],
+            })
+            .collect();
+            let table = transit
+                .table()
+                .title(vec!["", "Mail", "Average", "Median", "Fastest", "Slowest"])
+                .display()?;
+            println!("{}", table);
+
+            let table = stats
+                .kinds
+                .iter()
+                .map(|kind| {
+                    vec![
+                        kind_name(kind).cell(),
+                        kind.mail.cell(),
+                        kind.to_delivery.map_or(0, |transit| transit.mail).cell(),
+                        kind.to_delivery
+                            .map_or_else(|| "".cell(), |transit| duration_cell(transit.average)),
+                    ]
+                })
+                .collect::<Vec<_>>()
+                .table()
+                .title(vec!["Type", "Mail", "Delivered", "Average transit"])
+                .display()?;
+            println!("{}", table);
+
+            let table = stats
+                .statuses
+                .iter()
+                .map(|(status, mail)| vec![status.cell(), mail.cell()])
+                .collect::<Vec<_>>()
+                .table()
+                .title(vec!["Status", "Mail"])
+                .display()?;
+            println!("{}", table);
+
+            if !stats.facilities.is_empty() {
+                let table = stats
+                    .facilities
+                    .iter()
+                    .map(|facility| vec![facility.facility.clone().cell(), facility.events.cell()])
+                    .collect::<Vec<_>>()
+                    .table()
+                    .title(vec!["Facility", "Scans"])
+                    .display()?;
+                println!("{}", table);
+            }
+
+            if !stats.months.is_empty() {
+                let table = stats
+                    .months
+                    .iter()
+                    .map(|(month, mail)| vec![month.cell(), mail.cell()])
+                    .collect::<Vec<_>>()
+                    .table()
+                    .title(vec!["Month", "Mail"])
+                    .display()?;
+                println!("{}", table);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Everything `orphy fetch --all-profiles` found, added up.
+#[derive(Serialize)]
+struct Totals {
+    profiles: usize,
+    last_mailed: Option<DateTime<Utc>>,
+    mail: usize,
+    letters: usize,
+    packages: usize,
+    legacy: usize,
+}
+
+#[derive(Serialize)]
+struct AllProfiles<'a> {
+    total: Totals,
+    profiles: &'a [Summary],
+}
+
+/// Prints the overview of every profile for `orphy fetch --all-profiles`.
+pub fn print_summaries(format: Format, summaries: &[Summary]) -> Result<(), Error> {
+    let total = Totals {
+        profiles: summaries.len(),
+        last_mailed: summaries
+            .iter()
+            .filter_map(|summary| summary.last_mailed)
+            .max(),
+        mail: summaries.iter().map(|summary| summary.mail).sum(),
+        letters: summaries.iter().map(|summary| summary.letters).sum(),
+        packages: summaries.iter().map(|summary| summary.packages).sum(),
+        legacy: summaries.iter().map(|summary| summary.legacy).sum(),
+    };
+    match format {
+        Format::Json => print_json(&AllProfiles {
+            total,
+            profiles: summaries,
+        }),
+        Format::Ndjson => print_ndjson(summaries),
+        Format::Yaml => print_yaml(&AllProfiles {
+            total,
+            profiles: summaries,
+        }),
+        Format::Csv => print_csv(summaries),
+        Format::Table => {
+            let mut table: Vec<Vec<CellStruct>> = summaries
+                .iter()
+                .map(|summary| {
+                    vec![
+                        summary.profile.clone().cell(),
+                        summary.id.clone().unwrap_or_default().cell(),
+                        summary.instance.clone().cell(),
+                        summary.mail.cell(),
+                        summary.letters.cell(),
+                        summary.packages.cell(),
+                        summary.legacy.cell(),
+                    ]
+                })
+                .collect();
+            table.push(vec![
+                "total".cell().bold(true),
+                "".cell(),
+                "".cell(),
+                total.mail.cell().bold(true),
+                total.letters.cell().bold(true),
+                total.packages.cell().bold(true),
+                total.legacy.cell().bold(true),
+            ]);
+            let table = table
+                .table()
+                .title(vec![
+                    "Profile", "ID", "Instance", "Mail", "Letters", "Packages", "Legacy",
+                ])
+                .display()?;
+            println!("{}", table);
+            if let Some(last_mailed) = total.last_mailed {
+                println!("Last mailed: {}", last_mailed.naive_utc());
+            }
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ProfileRow {
+    pub name: String,
+    pub active: bool,
+    pub base: Option<String>,
+}
+
+/// Prints the saved profiles for `orphy profile list`. Api keys are left out on purpose.
+pub fn print_profiles(format: Format, profiles: &[ProfileRow]) -> Result<(), Error> {
+    match format {
+        Format::Json => print_json(profiles),
+        Format::Ndjson => print_ndjson(profiles),
+        Format::Yaml => print_yaml(profiles),
+        Format::Csv => print_csv(profiles),
+        Format::Table => {
+            if profiles.is_empty() {
+                println!(
+                    "You don't have any profiles! Add one using orphy profile add [name] [api key]"
+                );
+                return Ok(());
+            }
+            let table = profiles
+                .iter()
+                .map(|profile| {
+                    vec![
+                        if profile.active { "*" } else { "" }.cell(),
+                        profile.name.clone().cell(),
+                        profile
+                            .base
+                            .clone()
+                            .unwrap_or_else(|| String::from("default instance"))
+                            .cell(),
+                    ]
+                })
+                .collect::<Vec<_>>()
+                .table()
+                .title(vec!["", "Profile", "Instance"])
+                .display()?;
+            println!("{}", table);
+            Ok(())
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ChangeRow<'a> {
+    kind: &'static str,
+    id: Option<&'a str>,
+    title: String,
+    status: Option<&'a str>,
+    from: Option<&'a str>,
+    to: Option<&'a str>,
+    event_happened_at: Option<String>,
+    event_description: Option<&'a str>,
+    event_facility: Option<&'a str>,
+    event_location: Option<&'a str>,
+}
+
+impl<'a> From<&'a Change> for ChangeRow<'a> {
+    fn from(change: &'a Change) -> Self {
+        let letter = change.letter();
+        let mut row = Self {
+            kind: change.kind(),
+            id: letter.id.as_deref(),
+            title: letter_name(letter),
+            status: letter.status.as_deref(),
+            from: None,
+            to: None,
+            event_happened_at: None,
+            event_description: None,
+            event_facility: None,
+            event_location: None,
+        };
+        match change {
+            Change::NewLetter { .. } => {}
+            Change::StatusChanged { from, to, .. } => {
+                row.from = from.as_deref();
+                row.to = to.as_deref();
+            }
+            Change::NewEvent { event, .. } => {
+                row.event_happened_at = event.happened_at.map(|date| date.to_rfc3339());
+                row.event_description = event.description.as_deref();
+                row.event_facility = event.facility.as_deref();
+                row.event_location = event.location.as_deref();
+            }
+        }
+        row
+    }
+}
+
+/// Prints what `orphy changes` found since the last sync.
+pub fn print_changes(
+    format: Format,
+    changes: &[Change],
+    since: Option<DateTime<Utc>>,
+) -> Result<(), Error> {
+    match format {
+        Format::Json => print_json(changes),
+        Format::Ndjson => print_ndjson(changes),
+        Format::Yaml => print_yaml(changes),
+        Format::Csv => print_csv(changes.iter().map(ChangeRow::from)),
+        Format::Table => {
+            if changes.is_empty() {
+                match since {
+                    Some(since) => println!("Nothing changed since {}", since.naive_utc()),
+                    None => println!("You don't have any mail!"),
+                }
+                return Ok(());
+            }
+            match since {
+                Some(since) => println!("Changes since {}", since.naive_utc()),
+                None => println!("First sync! Here's everything orphy knows about"),
+            }
+            let table: Vec<Vec<CellStruct>> = changes
+                .iter()
+                .map(|change| {
+                    let letter = change.letter();
+                    vec![
+                        letter.id.as_deref().unwrap_or("no id").cell(),
+                        letter_name(letter).cell(),
+                        change.describe().cell(),
+                    ]
+                })
+                .collect();
+            let table = table
+                .table()
+                .title(vec!["ID", "Name", "Change"])
+                .display()?;
+            println!("{}", table);
+            Ok(())
+        }
+    }
+}
+
+/// Prints changes as they come in for `orphy watch`. json is printed one change per line since
+/// there's no end to the list.
+pub struct ChangeStream {
+    format: Format,
+    csv_header_written: bool,
+}
+
+impl ChangeStream {
+    pub fn new(format: Format) -> Self {
+        Self {
+            format,
+            csv_header_written: false,
+        }
+    }
+
+    pub fn print(&mut self, changes: &[Change]) -> Result<(), Error> {
+        match self.format {
+            Format::Json | Format::Ndjson => print_ndjson(changes)?,
+            Format::Yaml => {
+                for change in changes {
+                    print!("---\n{}", serde_yaml::to_string(change)?);
+                }
+            }
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .has_headers(!self.csv_header_written)
+                    .from_writer(stdout().lock());
+                for change in changes {
+                    writer.serialize(ChangeRow::from(change))?;
+                    self.csv_header_written = true;
+                }
+                writer.flush()?;
+            }
+            Format::Table => {
+                for change in changes {
+                    let letter = change.letter();
+                    println!(
+                        "[{}] {} {}: {}",
+                        Local::now().format("%Y-%m-%d %H:%M:%S"),
+                        letter.id.as_deref().unwrap_or("no id"),
+                        letter_name(letter),
+                        change.describe()
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use chrono::{DateTime, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How a [`MailClient`](crate::MailClient) retries requests that failed in a way that might go
+/// away on its own: connection problems, timeouts, 5xx answers and rate limiting.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Retry {
+    /// How many times a request is retried before giving up. 0 turns retrying off.
+    pub max_retries: u32,
+    /// How long to wait before the first retry. It doubles with each one after that.
+    pub base_delay: Duration,
+    /// The longest to ever wait between tries, `Retry-After` included. Rate limits that ask for
+    /// a longer wait aren't retried.
+    pub max_delay: Duration,
+}
+
+impl Retry {
+    /// Never retries.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// How long to wait before retry number `attempt` (counting from 1), unless the server said
+    /// otherwise.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay)
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Reads a `Retry-After` header, which is either a number of seconds or an http date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Spaces requests out so there are never more than a set number a second.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    interval: Option<Duration>,
+    next: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    /// Allows `per_second` requests a second. 0 means no limit.
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            interval: (per_second > 0).then(|| Duration::from_secs(1) / per_second),
+            next: Mutex::new(None),
+        }
+    }
+
+    /// Waits until it's this request's turn.
+    pub async fn wait(&self) {
+        let Some(interval) = self.interval else {
+            return;
+        };
+        let delay = {
+            let mut next = self.next.lock().unwrap();
+            let now = Instant::now();
+            let slot = next.map_or(now, |next| next.max(now));
+            *next = Some(slot + interval);
+            slot - now
+        };
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
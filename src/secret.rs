@@ -0,0 +1,126 @@
+use crate::cli::Profile;
+use anyhow::{Context, Error, bail};
+use argon2::Argon2;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use std::env;
+use std::process::Command;
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// An api key given through `ORPHY_API_KEY`, which wins over whatever the profile has saved.
+pub fn env_api_key() -> Option<String> {
+    env::var("ORPHY_API_KEY").ok().filter(|key| !key.is_empty())
+}
+
+/// Works out the api key a profile has saved: the output of its token command, its encrypted
+/// key, or the plain one, in that order. `None` means it hasn't got one.
+pub fn api_key(profile: &Profile) -> Result<Option<String>, Error> {
+    if let Some(command) = &profile.token_command {
+        return run_token_command(command).map(Some);
+    }
+    if let Some(encrypted) = &profile.encrypted_key {
+        let passphrase = passphrase("Passphrase for your api key: ")?;
+        return decrypt(encrypted, &passphrase).map(Some);
+    }
+    if profile.api_key.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(profile.api_key.clone()))
+}
+
+/// Runs a password manager (or anything else) through the shell and takes what it prints as the
+/// api key.
+pub fn run_token_command(command: &str) -> Result<String, Error> {
+    let output = if cfg!(windows) {
+        Command::new("cmd").args(["/C", command]).output()
+    } else {
+        Command::new("sh").args(["-c", command]).output()
+    }
+    .with_context(|| format!("Couldn't run the token command `{command}`"))?;
+
+    if !output.status.success() {
+        bail!(
+            "The token command `{command}` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let key = String::from_utf8(output.stdout)
+        .context("The token command didn't print text")?
+        .trim()
+        .to_string();
+    if key.is_empty() {
+        bail!("The token command `{command}` didn't print an api key");
+    }
+    Ok(key)
+}
+
+/// The passphrase from `ORPHY_PASSPHRASE`, or asked for on the terminal.
+pub fn passphrase(prompt: &str) -> Result<String, Error> {
+    if let Ok(passphrase) = env::var("ORPHY_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    rpassword::prompt_password(prompt).context("Couldn't read your passphrase")
+}
+
+/// Asks for a passphrase to encrypt with, twice so a typo doesn't lock the key away.
+pub fn new_passphrase() -> Result<String, Error> {
+    if let Ok(passphrase) = env::var("ORPHY_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = passphrase("Passphrase to encrypt your api key with: ")?;
+    if passphrase.is_empty() {
+        bail!("The passphrase can't be empty");
+    }
+    if passphrase != self::passphrase("Same passphrase again: ")? {
+        bail!("The passphrases didn't match");
+    }
+    Ok(passphrase)
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Key, Error> {
+    let mut key = Key::default();
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| anyhow::anyhow!("Couldn't derive a key from your passphrase: {err}"))?;
+    Ok(key)
+}
+
+/// Encrypts `api_key` with a key derived from `passphrase`. The salt and nonce are kept in front
+/// of the ciphertext, all base64 encoded.
+pub fn encrypt(api_key: &str, passphrase: &str) -> Result<String, Error> {
+    let mut salt = [0; SALT_LEN];
+    OsRng.fill_bytes(&mut salt);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt)?);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, api_key.as_bytes())
+        .map_err(|_| anyhow::anyhow!("Couldn't encrypt your api key"))?;
+
+    let mut sealed = Vec::with_capacity(SALT_LEN + NONCE_LEN + ciphertext.len());
+    sealed.extend_from_slice(&salt);
+    sealed.extend_from_slice(&nonce);
+    sealed.extend_from_slice(&ciphertext);
+    Ok(STANDARD.encode(sealed))
+}
+
+/// Undoes [`encrypt`].
+pub fn decrypt(sealed: &str, passphrase: &str) -> Result<String, Error> {
+    let sealed = STANDARD
+        .decode(sealed)
+        .context("The encrypted api key in your config is damaged")?;
+    if sealed.len() < SALT_LEN + NONCE_LEN {
+        bail!("The encrypted api key in your config is damaged");
+    }
+    let (salt, rest) = sealed.split_at(SALT_LEN);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, salt)?);
+    let api_key = cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| anyhow::anyhow!("Couldn't decrypt your api key, is the passphrase right?"))?;
+    String::from_utf8(api_key).context("The encrypted api key in your config is damaged")
+}
@@ -0,0 +1,407 @@
+use crate::output::letter_name;
+use anyhow::{Context, Error};
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{Html, IntoResponse, Json, Response};
+use axum::routing::get;
+use chrono::{DateTime, Local, Utc};
+use orphy::metrics;
+use orphy::{Event, Letter, MailClient, MailError, MailType, RequestMetrics};
+use std::collections::HashMap;
+use std::future::IntoFuture;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tokio::signal;
+
+/// The mail `orphy serve` and `orphy exporter` hand out, replaced on every refresh.
+struct Snapshot {
+    letters: Vec<Letter>,
+    refreshed_at: DateTime<Utc>,
+    /// Why the last refresh failed, if it did. The letters are still the ones from before it.
+    error: Option<String>,
+    /// How many refreshes have failed.
+    refresh_errors: u64,
+    /// How requests have gone as of the last refresh. Only refreshes send any, so this is
+    /// up to date.
+    requests: RequestMetrics,
+}
+
+type Shared = Arc<RwLock<Snapshot>>;
+
+const STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 0 auto; max-width: 60rem; padding: 1rem; }
+table { border-collapse: collapse; width: 100%; }
+th, td { border-bottom: 1px solid #ddd; padding: 0.4rem; text-align: left; vertical-align: top; }
+dt { font-weight: bold; }
+dd { margin: 0 0 0.5rem 0; }
+ol.timeline { border-left: 2px solid #ec3750; list-style: none; padding-left: 1rem; }
+ol.timeline li { margin-bottom: 0.8rem; }
+.muted, footer { color: #666; }
+.error { color: #ec3750; }
+";
+
+/// Serves the mail of `client` on `bind` until Ctrl-C, fetching it again every `interval`.
+pub async fn run(client: &MailClient, bind: SocketAddr, interval: Duration) -> Result<(), Error> {
+    listen(client, bind, interval, dashboard(), "your mail", "").await
+}
+
+/// Serves Prometheus metrics about the mail of `client` on `bind` until Ctrl-C, fetching it again
+/// every `interval`.
+pub async fn export(
+    client: &MailClient,
+    bind: SocketAddr,
+    interval: Duration,
+) -> Result<(), Error> {
+    let router = Router::new().route("/metrics", get(prometheus));
+    listen(client, bind, interval, router, "metrics", "/metrics").await
+}
+
+/// Serves `router` with the mail of `client`, saying it's serving `what` at `path`.
+async fn listen(
+    client: &MailClient,
+    bind: SocketAddr,
+    interval: Duration,
+    router: Router<Shared>,
+    what: &str,
+    path: &str,
+) -> Result<(), Error> {
+    let letters = fetch(client).await?;
+    let state = Arc::new(RwLock::new(Snapshot {
+        letters,
+        refreshed_at: client.take_cached_at().unwrap_or_else(Utc::now),
+        error: None,
+        refresh_errors: 0,
+        requests: client.request_metrics(),
+    }));
+
+    let listener = TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Couldn't listen on {bind}"))?;
+    eprintln!(
+        "Serving {what} on http://{}{path}. Press Ctrl-C to stop.",
+        listener.local_addr()?
+    );
+    let server = axum::serve(listener, router.with_state(state.clone()))
+        .with_graceful_shutdown(async {
+            let _ = signal::ctrl_c().await;
+        })
+        .into_future();
+    tokio::select! {
+        result = server => result?,
+        _ = refresh(client, &state, interval) => {}
+    }
+    Ok(())
+}
+
+fn dashboard() -> Router<Shared> {
+    Router::new()
+        .route("/", get(index))
+        .route("/mail/{id}", get(details))
+        .route("/api/public/v1/mail", get(all_mail))
+        .route("/api/public/v1/letters", get(letters))
+        .route("/api/public/v1/letters/{id}", get(letter))
+        .route("/api/public/v1/packages", get(packages))
+        .route("/api/public/v1/packages/{id}", get(package))
+        .route("/api/public/v1/lsv", get(legacy_records))
+        .route("/api/public/v1/lsv/{lsv_type}/{id}", get(legacy_record))
+}
+
+async fn fetch(client: &MailClient) -> Result<Vec<Letter>, MailError> {
+    let mail = client.get_mail_with_events(None).await?;
+    for err in &mail.errors {
+        eprintln!("Skipping {err}");
+    }
+    Ok(mail.letters)
+}
+
+/// Fetches the mail every `interval` forever. A failed refresh keeps the mail from the last one
+/// that worked, and the dashboard says so. Mail that came from the cache (`--offline`) is as old
+/// as when it was cached.
+async fn refresh(client: &MailClient, state: &Shared, interval: Duration) {
+    loop {
+        tokio::time::sleep(interval).await;
+        client.take_cached_at();
+        let result = fetch(client).await;
+        let mut snapshot = state.write().unwrap();
+        snapshot.requests = client.request_metrics();
+        match result {
+            Ok(letters) => {
+                snapshot.letters = letters;
+                snapshot.refreshed_at = client.take_cached_at().unwrap_or_else(Utc::now);
+                snapshot.error = None;
+            }
+            Err(err) => {
+                eprintln!("Couldn't refresh your mail: {err}");
+                snapshot.error = Some(err.to_string());
+                snapshot.refresh_errors += 1;
+            }
+        }
+    }
+}
+
+/// A list endpoint, with the letters under `name` like Hack Club Mail does.
+fn list(state: &Shared, kind: Option<MailType>, name: &str) -> Response {
+    let snapshot = state.read().unwrap();
+    let letters: Vec<&Letter> = snapshot
+        .letters
+        .iter()
+        .filter(|letter| kind.is_none_or(|kind| kind.matches(letter)))
+        .collect();
+    Json(HashMap::from([(name, letters)])).into_response()
+}
+
+/// A single record endpoint, with the letter under `name` like Hack Club Mail does.
+fn record(state: &Shared, kind: MailType, id: &str, name: &str) -> Response {
+    let snapshot = state.read().unwrap();
+    match snapshot
+        .letters
+        .iter()
+        .find(|letter| letter.id.as_deref() == Some(id) && kind.matches(letter))
+    {
+        Some(letter) => Json(HashMap::from([(name, letter)])).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(HashMap::from([("error", "not found")])),
+        )
+            .into_response(),
+    }
+}
+
+async fn all_mail(State(state): State<Shared>) -> Response {
+    list(&state, None, "mail")
+}
+
+async fn letters(State(state): State<Shared>) -> Response {
+    list(&state, Some(MailType::Letter), "letters")
+}
+
+async fn packages(State(state): State<Shared>) -> Response {
+    list(&state, Some(MailType::Package), "packages")
+}
+
+async fn legacy_records(State(state): State<Shared>) -> Response {
+    list(
+        &state,
+        Some(MailType::Legacy),
+        "legacy_shipment_viewer_records",
+    )
+}
+
+async fn letter(State(state): State<Shared>, Path(id): Path<String>) -> Response {
+    record(&state, MailType::Letter, &id, "letter")
+}
+
+async fn package(State(state): State<Shared>, Path(id): Path<String>) -> Response {
+    record(&state, MailType::Package, &id, "package")
+}
+
+async fn legacy_record(
+    State(state): State<Shared>,
+    Path((_, id)): Path<(String, String)>,
+) -> Response {
+    record(
+        &state,
+        MailType::Legacy,
+        &id,
+        "legacy_shipment_viewer_record",
+    )
+}
+
+async fn prometheus(State(state): State<Shared>) -> Response {
+    let snapshot = state.read().unwrap();
+    let mut text = metrics::prometheus(&snapshot.letters, &snapshot.requests, Utc::now());
+    text += &metrics::refreshes(snapshot.refreshed_at, snapshot.refresh_errors);
+    (
+        [(CONTENT_TYPE, "text/plain; version=0.0.4; charset=utf-8")],
+        text,
+    )
+        .into_response()
+}
+
+/// The dashboard, a table of every piece of mail.
+async fn index(State(state): State<Shared>) -> Html<String> {
+    let snapshot = state.read().unwrap();
+    let mut rows = String::new();
+    for letter in &snapshot.letters {
+        let name = escape(&letter_name(letter));
+        let name = match &letter.id {
+            Some(id) => format!("<a href=\"/mail/{}\">{name}</a>", encode(id)),
+            None => name,
+        };
+        let latest = match letter.latest_event() {
+            Some(event) => describe(event),
+            None => String::from("<span class=\"muted\">no events</span>"),
+        };
+        rows.push_str(&format!(
+            "<tr><td>{name}</td><td>{}</td><td>{}</td><td>{}</td><td>{latest}</td></tr>\n",
+            escape(letter.letter_type.as_deref().unwrap_or("unknown")),
+            escape(letter.status.as_deref().unwrap_or("no status")),
+            letter.created_at.map(time).unwrap_or_default(),
+        ));
+    }
+    let body = if snapshot.letters.is_empty() {
+        String::from("<p>No mail yet.</p>")
+    } else {
+        format!(
+            "<table>\n<tr><th>Name</th><th>Type</th><th>Status</th><th>Created</th>\
+             <th>Latest event</th></tr>\n{rows}</table>"
+        )
+    };
+    Html(page(&snapshot, "Mail", &format!("<h1>Mail</h1>\n{body}")))
+}
+
+/// The details and timeline of one piece of mail.
+async fn details(State(state): State<Shared>, Path(id): Path<String>) -> Response {
+    let snapshot = state.read().unwrap();
+    let Some(letter) = snapshot
+        .letters
+        .iter()
+        .find(|letter| letter.id.as_deref() == Some(id.as_str()))
+    else {
+        let body = format!(
+            "<h1>Not found</h1>\n<p>There's no mail with the id {}. \
+             <a href=\"/\">Back to the list</a></p>",
+            escape(&id)
+        );
+        return (
+            StatusCode::NOT_FOUND,
+            Html(page(&snapshot, "Not found", &body)),
+        )
+            .into_response();
+    };
+
+    let name = escape(&letter_name(letter));
+    let mut fields = vec![("Id", escape(&id))];
+    if let Some(letter_type) = &letter.letter_type {
+        let kind = match &letter.letter_subtype {
+            Some(subtype) => format!("{letter_type} ({subtype})"),
+            None => letter_type.clone(),
+        };
+        fields.push(("Type", escape(&kind)));
+    }
+    if let Some(status) = &letter.status {
+        fields.push(("Status", escape(status)));
+    }
+    if let Some(created_at) = letter.created_at {
+        fields.push(("Created", time(created_at)));
+    }
+    if let Some(tracking_number) = &letter.tracking_number {
+        let tracking_number = escape(tracking_number);
+        fields.push((
+            "Tracking number",
+            match letter.tracking_url().filter(|link| is_web(link)) {
+                Some(link) => format!("<a href=\"{}\">{tracking_number}</a>", escape(&link)),
+                None => tracking_number,
+            },
+        ));
+    }
+    if let Some(carrier) = letter.carrier() {
+        fields.push(("Carrier", carrier.name().to_string()));
+    }
+    if let Some(public_url) = letter.public_url.as_deref().filter(|link| is_web(link)) {
+        let public_url = escape(public_url);
+        fields.push((
+            "Public url",
+            format!("<a href=\"{public_url}\">{public_url}</a>"),
+        ));
+    }
+    if let Some(tags) = &letter.tags
+        && !tags.is_empty()
+    {
+        fields.push(("Tags", escape(&tags.join(", "))));
+    }
+    let fields: String = fields
+        .into_iter()
+        .map(|(name, value)| format!("<dt>{name}</dt><dd>{value}</dd>\n"))
+        .collect();
+
+    let events = letter.timeline();
+    let timeline = if events.is_empty() {
+        String::from("<p>No events yet.</p>")
+    } else {
+        let items: String = events
+            .into_iter()
+            .map(|event| format!("<li>{}</li>\n", describe(event)))
+            .collect();
+        format!("<ol class=\"timeline\">\n{items}</ol>")
+    };
+
+    let body = format!(
+        "<p><a href=\"/\">&larr; All mail</a></p>\n<h1>{name}</h1>\n<dl>\n{fields}</dl>\n\
+         <h2>Events</h2>\n{timeline}"
+    );
+    Html(page(&snapshot, &name, &body)).into_response()
+}
+
+/// A whole html page around `body`. `title` has to be escaped already.
+fn page(snapshot: &Snapshot, title: &str, body: &str) -> String {
+    let mut footer = format!("Updated {}", time(snapshot.refreshed_at));
+    if let Some(err) = &snapshot.error {
+        footer.push_str(&format!(
+            " <span class=\"error\">(the last refresh failed: {})</span>",
+            escape(err)
+        ));
+    }
+    format!(
+        "<!doctype html>
+<html lang=\"en\">
+<head>
+<meta charset=\"utf-8\">
+<meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">
+<title>{title} · orphy</title>
+<style>{STYLE}</style>
+</head>
+<body>
+<main>
+{body}
+</main>
+<footer>{footer}</footer>
+</body>
+</html>
+"
+    )
+}
+
+fn describe(event: &Event) -> String {
+    let mut line = format!(
+        "<strong>{}</strong>",
+        escape(event.description.as_deref().unwrap_or("no description"))
+    );
+    if let Some(happened_at) = event.happened_at {
+        line.push_str(&format!("<br>{}", time(happened_at)));
+    }
+    if let Some(place) = event.place(" · ") {
+        line.push_str(&format!(" <span class=\"muted\">{}</span>", escape(&place)));
+    }
+    line
+}
+
+fn time(date: DateTime<Utc>) -> String {
+    format!(
+        "<time datetime=\"{}\">{}</time>",
+        date.to_rfc3339(),
+        date.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+    )
+}
+
+/// Only links to web pages are put on the dashboard, so a record can't sneak in a
+/// `javascript:` one.
+fn is_web(link: &str) -> bool {
+    link.starts_with("https://") || link.starts_with("http://")
+}
+
+fn encode(id: &str) -> String {
+    url::form_urlencoded::byte_serialize(id.as_bytes()).collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
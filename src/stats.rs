@@ -0,0 +1,176 @@
+use crate::mail::Letter;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// How many facilities [`Stats::facilities`] keeps.
+pub const TOP_FACILITIES: usize = 10;
+
+/// Numbers about a list of mail and how long it took to get anywhere. Mail needs its events for
+/// most of them, see [`MailClient::get_mail_with_events`](crate::MailClient::get_mail_with_events).
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Stats {
+    /// How much mail the numbers are about.
+    pub mail: usize,
+    /// From being created to the first scan.
+    pub to_first_event: Option<Transit>,
+    /// From being created to being delivered.
+    pub to_delivery: Option<Transit>,
+    /// Every type and subtype of mail, with how long it took to be delivered.
+    pub kinds: Vec<KindStats>,
+    /// How much mail has each status. Mail without one is counted as "no status".
+    pub statuses: BTreeMap<String, usize>,
+    /// The facilities with the most scans, most first.
+    pub facilities: Vec<FacilityCount>,
+    /// How much mail was created each month, keyed by `YYYY-MM`.
+    pub months: BTreeMap<String, usize>,
+}
+
+/// How long a step took over a number of pieces of mail. Durations are serialized as whole
+/// seconds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+pub struct Transit {
+    pub mail: usize,
+    #[serde(rename = "average_seconds", serialize_with = "seconds")]
+    pub average: Duration,
+    #[serde(rename = "median_seconds", serialize_with = "seconds")]
+    pub median: Duration,
+    #[serde(rename = "fastest_seconds", serialize_with = "seconds")]
+    pub fastest: Duration,
+    #[serde(rename = "slowest_seconds", serialize_with = "seconds")]
+    pub slowest: Duration,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct KindStats {
+    #[serde(rename = "type")]
+    pub letter_type: Option<String>,
+    #[serde(rename = "subtype")]
+    pub letter_subtype: Option<String>,
+    pub mail: usize,
+    /// From being created to being delivered, for the mail of this kind that was.
+    pub to_delivery: Option<Transit>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FacilityCount {
+    pub facility: String,
+    pub events: usize,
+}
+
+// type and subtype
+type Kind<'a> = (Option<&'a str>, Option<&'a str>);
+
+impl Transit {
+    /// Sums up a list of durations, `None` if it's empty.
+    pub fn new(mut durations: Vec<Duration>) -> Option<Self> {
+        durations.sort();
+        let (fastest, slowest) = (*durations.first()?, *durations.last()?);
+        let middle = durations.len() / 2;
+        let median = if durations.len().is_multiple_of(2) {
+            (durations[middle - 1] + durations[middle]) / 2
+        } else {
+            durations[middle]
+        };
+        Some(Self {
+            mail: durations.len(),
+            average: durations.iter().sum::<Duration>() / durations.len() as u32,
+            median,
+            fastest,
+            slowest,
+        })
+    }
+}
+
+impl Stats {
+    pub fn new(letters: &[Letter]) -> Self {
+        let mut kinds: BTreeMap<Kind, (usize, Vec<Duration>)> = BTreeMap::new();
+        let mut statuses = BTreeMap::new();
+        let mut facilities: HashMap<&str, usize> = HashMap::new();
+        let mut months = BTreeMap::new();
+        let mut to_first_event = Vec::new();
+        let mut to_delivery = Vec::new();
+
+        for letter in letters {
+            let kind = kinds
+                .entry((
+                    letter.letter_type.as_deref(),
+                    letter.letter_subtype.as_deref(),
+                ))
+                .or_default();
+            kind.0 += 1;
+
+            *statuses
+                .entry(
+                    letter
+                        .status
+                        .clone()
+                        .unwrap_or_else(|| String::from("no status")),
+                )
+                .or_default() += 1;
+
+            for event in letter.events.iter().flatten() {
+                if let Some(facility) = &event.facility {
+                    *facilities.entry(facility).or_default() += 1;
+                }
+            }
+
+            let Some(created_at) = letter.created_at else {
+                continue;
+            };
+            *months
+                .entry(created_at.format("%Y-%m").to_string())
+                .or_default() += 1;
+
+            // scans from before the mail was created are clock trouble, not instant delivery
+            let since_created = |date: DateTime<Utc>| (date - created_at).to_std();
+            if let Some(Ok(duration)) = letter
+                .timeline()
+                .first()
+                .and_then(|event| event.happened_at)
+                .map(since_created)
+            {
+                to_first_event.push(duration);
+            }
+            if let Some(Ok(duration)) = letter.delivered_at().map(since_created) {
+                to_delivery.push(duration);
+                kind.1.push(duration);
+            }
+        }
+
+        let mut facilities: Vec<FacilityCount> = facilities
+            .into_iter()
+            .map(|(facility, events)| FacilityCount {
+                facility: facility.to_string(),
+                events,
+            })
+            .collect();
+        facilities.sort_by(|a, b| b.events.cmp(&a.events).then(a.facility.cmp(&b.facility)));
+        facilities.truncate(TOP_FACILITIES);
+
+        Self {
+            mail: letters.len(),
+            to_first_event: Transit::new(to_first_event),
+            to_delivery: Transit::new(to_delivery),
+            kinds: kinds
+                .into_iter()
+                .map(
+                    |((letter_type, letter_subtype), (mail, durations))| KindStats {
+                        letter_type: letter_type.map(String::from),
+                        letter_subtype: letter_subtype.map(String::from),
+                        mail,
+                        to_delivery: Transit::new(durations),
+                    },
+                )
+                .collect(),
+            statuses,
+            facilities,
+            months,
+        }
+    }
+}
+
+fn seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
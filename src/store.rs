@@ -0,0 +1,193 @@
+use crate::changes::{Change, diff};
+use crate::error::StoreError;
+use crate::mail::{Event, Letter};
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::Path;
+
+/// A local history of every piece of mail orphy has seen, used to work out what changed between
+/// syncs.
+pub struct Store {
+    conn: Connection,
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS letters (
+    id TEXT PRIMARY KEY,
+    status TEXT,
+    data TEXT NOT NULL,
+    has_events INTEGER NOT NULL DEFAULT 0,
+    first_seen TEXT NOT NULL,
+    last_seen TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS events (
+    letter_id TEXT NOT NULL REFERENCES letters(id),
+    key TEXT NOT NULL,
+    happened_at TEXT,
+    data TEXT NOT NULL,
+    first_seen TEXT NOT NULL,
+    PRIMARY KEY (letter_id, key)
+);
+CREATE TABLE IF NOT EXISTS status_changes (
+    letter_id TEXT NOT NULL REFERENCES letters(id),
+    from_status TEXT,
+    to_status TEXT,
+    changed_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS syncs (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    synced_at TEXT NOT NULL,
+    letters INTEGER NOT NULL,
+    changes INTEGER NOT NULL
+);
+";
+
+impl Store {
+    /// Opens (or creates) the history database at `path`.
+    pub fn open(path: &Path) -> Result<Self, StoreError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::init(Connection::open(path)?)
+    }
+
+    /// A throwaway store that only lives as long as it does.
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, StoreError> {
+        conn.execute_batch(SCHEMA)?;
+        Ok(Self { conn })
+    }
+
+    /// When the last sync finished, if there was one.
+    pub fn last_sync(&self) -> Result<Option<DateTime<Utc>>, StoreError> {
+        let synced_at: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT synced_at FROM syncs ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(synced_at
+            .and_then(|date| DateTime::parse_from_rfc3339(&date).ok())
+            .map(|date| date.with_timezone(&Utc)))
+    }
+
+    /// The last known state of a piece of mail.
+    pub fn letter(&self, id: &str) -> Result<Option<Letter>, StoreError> {
+        load_letter(&self.conn, id)
+    }
+
+    /// Everything in the history, newest first.
+    pub fn letters(&self) -> Result<Vec<Letter>, StoreError> {
+        let mut statement = self
+            .conn
+            .prepare("SELECT id FROM letters ORDER BY first_seen DESC, rowid DESC")?;
+        let ids = statement
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut letters = Vec::with_capacity(ids.len());
+        for id in ids {
+            letters.extend(load_letter(&self.conn, &id)?);
+        }
+        Ok(letters)
+    }
+
+    /// Records the current state of `letters` and returns what changed since the last sync.
+    ///
+    /// Mail without an id can't be tracked and is ignored. Events are only ever added, so syncing
+    /// a letter fetched without its events keeps the ones already known.
+    pub fn sync(&mut self, letters: &[Letter]) -> Result<Vec<Change>, StoreError> {
+        let now = Utc::now().to_rfc3339();
+        let tx = self.conn.transaction()?;
+        let mut changes = Vec::new();
+
+        for letter in letters {
+            let Some(id) = &letter.id else {
+                continue;
+            };
+            let previous = load_letter(&tx, id)?;
+            let letter_changes = diff(previous.as_ref(), letter);
+
+            let data = serde_json::to_string(&Letter {
+                events: None,
+                ..letter.clone()
+            })?;
+            tx.execute(
+                "INSERT INTO letters (id, status, data, has_events, first_seen, last_seen)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT (id) DO UPDATE SET
+                    status = excluded.status,
+                    data = excluded.data,
+                    has_events = max(has_events, excluded.has_events),
+                    last_seen = excluded.last_seen",
+                params![id, letter.status, data, letter.events.is_some(), now],
+            )?;
+
+            for change in &letter_changes {
+                if let Change::StatusChanged { from, to, .. } = change {
+                    tx.execute(
+                        "INSERT INTO status_changes (letter_id, from_status, to_status, changed_at)
+                         VALUES (?1, ?2, ?3, ?4)",
+                        params![id, from, to, now],
+                    )?;
+                }
+            }
+
+            for event in letter.events.iter().flatten() {
+                tx.execute(
+                    "INSERT OR IGNORE INTO events (letter_id, key, happened_at, data, first_seen)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        id,
+                        event.key(),
+                        event.happened_at.map(|date| date.to_rfc3339()),
+                        serde_json::to_string(event)?,
+                        now
+                    ],
+                )?;
+            }
+
+            changes.extend(letter_changes);
+        }
+
+        tx.execute(
+            "INSERT INTO syncs (synced_at, letters, changes) VALUES (?1, ?2, ?3)",
+            params![now, letters.len() as i64, changes.len() as i64],
+        )?;
+        tx.commit()?;
+        Ok(changes)
+    }
+}
+
+fn load_letter(conn: &Connection, id: &str) -> Result<Option<Letter>, StoreError> {
+    let row: Option<(String, bool)> = conn
+        .query_row(
+            "SELECT data, has_events FROM letters WHERE id = ?1",
+            [id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((data, has_events)) = row else {
+        return Ok(None);
+    };
+
+    let mut letter: Letter = serde_json::from_str(&data)?;
+    if has_events {
+        let mut statement =
+            conn.prepare("SELECT data FROM events WHERE letter_id = ?1 ORDER BY rowid")?;
+        let events = statement
+            .query_map([id], |row| row.get::<_, String>(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        letter.events = Some(
+            events
+                .iter()
+                .map(|event| serde_json::from_str::<Event>(event))
+                .collect::<Result<_, _>>()?,
+        );
+    }
+    Ok(Some(letter))
+}
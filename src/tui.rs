@@ -0,0 +1,514 @@
+use crate::output::letter_name;
+use anyhow::Error;
+use chrono::{DateTime, Local, Utc};
+use orphy::{Letter, MailClient, MailError, MailFilter, MailList, MailType};
+use ratatui::crossterm::event::{self, Event as TermEvent, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, Paragraph, Row, Table, TableState, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+/// State of the `orphy tui` mailbox browser.
+struct App {
+    letters: Vec<Letter>,
+    details: HashMap<String, Letter>,
+    /// Ids whose details are being fetched.
+    loading: HashSet<String>,
+    mail_type: Option<MailType>,
+    search: String,
+    searching: bool,
+    table: TableState,
+    status: String,
+}
+
+impl App {
+    fn new() -> Self {
+        Self {
+            letters: Vec::new(),
+            details: HashMap::new(),
+            loading: HashSet::new(),
+            mail_type: None,
+            search: String::new(),
+            searching: false,
+            table: TableState::default().with_selected(0),
+            status: String::new(),
+        }
+    }
+
+    /// The letters that match the type filter and search, in the order they're listed. The search
+    /// looks through titles and tags like `orphy mail --search`, and ids.
+    fn visible(&self) -> Vec<&Letter> {
+        let filter = MailFilter {
+            search: Some(self.search.clone()),
+            ..Default::default()
+        };
+        let search = self.search.to_lowercase();
+        self.letters
+            .iter()
+            .filter(|letter| self.mail_type.is_none_or(|kind| kind.matches(letter)))
+            .filter(|letter| {
+                filter.matches(letter)
+                    || letter
+                        .id
+                        .as_deref()
+                        .is_some_and(|id| id.to_lowercase().contains(&search))
+            })
+            .collect()
+    }
+
+    fn selected(&self) -> Option<&Letter> {
+        self.table
+            .selected()
+            .and_then(|index| self.visible().get(index).copied())
+    }
+
+    fn select(&mut self, offset: isize) {
+        let len = self.visible().len();
+        if len == 0 {
+            self.table.select(None);
+            return;
+        }
+        let current = self.table.selected().unwrap_or(0) as isize;
+        let next = (current + offset).clamp(0, len as isize - 1);
+        self.table.select(Some(next as usize));
+    }
+
+    fn cycle_type(&mut self) {
+        self.mail_type = match self.mail_type {
+            None => Some(MailType::Letter),
+            Some(MailType::Letter) => Some(MailType::Package),
+            Some(MailType::Package) => Some(MailType::Legacy),
+            Some(MailType::Legacy) => None,
+        };
+        self.table.select(Some(0));
+        self.select(0);
+    }
+
+    /// Shows the mail a refresh came back with.
+    fn refreshed(&mut self, result: Result<MailList, MailError>, cached_at: Option<DateTime<Utc>>) {
+        match result {
+            Ok(mail) => {
+                self.status = if mail.errors.is_empty() {
+                    format!("Loaded {} pieces of mail", mail.letters.len())
+                } else {
+                    format!(
+                        "Loaded {} pieces of mail, skipped {} malformed records",
+                        mail.letters.len(),
+                        mail.errors.len()
+                    )
+                };
+                if let Some(cached_at) = cached_at {
+                    self.status += &format!(
+                        " (cached at {})",
+                        cached_at.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                    );
+                }
+                self.letters = mail.letters;
+                self.details.clear();
+                self.select(0);
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+    }
+
+    /// The id and path of the selected mail if its details haven't been fetched yet, marking them
+    /// as being fetched.
+    fn next_load(&mut self) -> Option<(String, Option<String>)> {
+        let (id, path) = self
+            .selected()
+            .and_then(|letter| Some((letter.id.clone()?, letter.path.clone())))?;
+        if self.details.contains_key(&id) || !self.loading.insert(id.clone()) {
+            return None;
+        }
+        Some((id, path))
+    }
+
+    /// Keeps the details of `id` once they've been fetched.
+    fn loaded(&mut self, id: String, path: Option<String>, result: Result<Letter, MailError>) {
+        self.loading.remove(&id);
+        match result {
+            Ok(letter) => {
+                let path = letter.path.clone().or(path);
+                self.details.insert(id, Letter { path, ..letter });
+            }
+            Err(err) => {
+                // keep what the list had so it isn't fetched again on every key press
+                let listed = self
+                    .letters
+                    .iter()
+                    .find(|letter| letter.id.as_deref() == Some(&id))
+                    .cloned()
+                    .unwrap_or_default();
+                self.details.insert(id, listed);
+                self.status = err.to_string();
+            }
+        }
+    }
+}
+
+/// What the event loop waits on: the keyboard, and fetches running in the background.
+enum Message {
+    Input(io::Result<TermEvent>),
+    Refreshed(Result<MailList, MailError>, Option<DateTime<Utc>>),
+    Loaded {
+        id: String,
+        path: Option<String>,
+        result: Box<Result<Letter, MailError>>,
+    },
+}
+
+/// Runs the full screen mailbox browser until the user quits.
+pub async fn run(client: MailClient) -> Result<(), Error> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, Arc::new(client)).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(terminal: &mut DefaultTerminal, client: Arc<MailClient>) -> Result<(), Error> {
+    let (messages, mut inbox) = unbounded_channel();
+    read_input(messages.clone());
+
+    let mut app = App::new();
+    app.status = String::from("Loading your mail...");
+    refresh(&client, &messages);
+
+    loop {
+        if let Some((id, path)) = app.next_load() {
+            load(&client, &messages, id, path);
+        }
+        terminal.draw(|frame| draw(frame, &mut app))?;
+
+        let key = match inbox.recv().await {
+            Some(Message::Input(Ok(TermEvent::Key(key)))) if key.kind == KeyEventKind::Press => key,
+            Some(Message::Input(Ok(_))) => continue,
+            Some(Message::Input(Err(err))) => return Err(err.into()),
+            Some(Message::Refreshed(result, cached_at)) => {
+                app.refreshed(result, cached_at);
+                continue;
+            }
+            Some(Message::Loaded { id, path, result }) => {
+                app.loaded(id, path, *result);
+                continue;
+            }
+            None => return Ok(()),
+        };
+        // raw mode turns Ctrl-C into a key press instead of a signal
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            return Ok(());
+        }
+
+        if app.searching {
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => app.searching = false,
+                KeyCode::Backspace => {
+                    app.search.pop();
+                }
+                KeyCode::Char(c) => app.search.push(c),
+                _ => {}
+            }
+            app.select(0);
+            continue;
+        }
+
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+            KeyCode::Down | KeyCode::Char('j') => app.select(1),
+            KeyCode::Up | KeyCode::Char('k') => app.select(-1),
+            KeyCode::PageDown => app.select(10),
+            KeyCode::PageUp => app.select(-10),
+            KeyCode::Home | KeyCode::Char('g') => app.select(isize::MIN / 2),
+            KeyCode::End | KeyCode::Char('G') => app.select(isize::MAX / 2),
+            KeyCode::Char('t') | KeyCode::Tab => app.cycle_type(),
+            KeyCode::Char('/') => {
+                app.searching = true;
+                app.search.clear();
+            }
+            KeyCode::Char('r') => {
+                app.status = String::from("Refreshing...");
+                refresh(&client, &messages);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads the keyboard on its own thread, since crossterm can only wait for it by blocking. It
+/// stops once the event loop is gone.
+fn read_input(messages: UnboundedSender<Message>) {
+    std::thread::spawn(move || {
+        while !messages.is_closed() {
+            let event = match event::poll(Duration::from_millis(100)) {
+                Ok(false) => continue,
+                Ok(true) => event::read(),
+                Err(err) => Err(err),
+            };
+            let failed = event.is_err();
+            if messages.send(Message::Input(event)).is_err() || failed {
+                return;
+            }
+        }
+    });
+}
+
+/// Fetches the list of mail in the background.
+fn refresh(client: &Arc<MailClient>, messages: &UnboundedSender<Message>) {
+    let client = client.clone();
+    let messages = messages.clone();
+    tokio::spawn(async move {
+        // details loaded from the cache since the last refresh don't make this list old
+        client.take_cached_at();
+        let result = client.get_mail(None).await;
+        let _ = messages.send(Message::Refreshed(result, client.take_cached_at()));
+    });
+}
+
+/// Fetches the details and events of one piece of mail in the background.
+fn load(
+    client: &Arc<MailClient>,
+    messages: &UnboundedSender<Message>,
+    id: String,
+    path: Option<String>,
+) {
+    let client = client.clone();
+    let messages = messages.clone();
+    tokio::spawn(async move {
+        // the listed path goes straight to the record, legacy ids alone would need all of /mail
+        let result = match path.clone() {
+            Some(path) => client.get_mail_by_path(path).await,
+            None => client.get_by_id(&id, None).await,
+        };
+        let _ = messages.send(Message::Loaded {
+            id,
+            path,
+            result: Box::new(result),
+        });
+    });
+}
+
+fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, footer] =
+        Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+    let [list, details] =
+        Layout::horizontal([Constraint::Percentage(50), Constraint::Percentage(50)]).areas(main);
+
+    draw_list(frame, app, list);
+    draw_details(frame, app, details);
+
+    let footer_text = if app.searching {
+        format!("search: {}_", app.search)
+    } else {
+        format!(
+            "q quit  j/k move  t type  / search  r refresh  |  {}",
+            app.status
+        )
+    };
+    frame.render_widget(Paragraph::new(footer_text).dim(), footer);
+}
+
+fn draw_list(frame: &mut Frame, app: &mut App, area: Rect) {
+    let rows: Vec<Row> = app
+        .visible()
+        .into_iter()
+        .map(|letter| {
+            Row::new(vec![
+                letter_name(letter),
+                letter
+                    .letter_type
+                    .clone()
+                    .unwrap_or_else(|| String::from("no type")),
+                letter
+                    .status
+                    .clone()
+                    .unwrap_or_else(|| String::from("no status")),
+                letter
+                    .created_at
+                    .map(|date| date.date_naive().to_string())
+                    .unwrap_or_else(|| String::from("no date")),
+            ])
+        })
+        .collect();
+
+    let filter = match app.mail_type {
+        None => "all mail",
+        Some(MailType::Letter) => "letters",
+        Some(MailType::Package) => "packages",
+        Some(MailType::Legacy) => "legacy",
+    };
+    let mut title = format!(" Mail ({filter}) ");
+    if !app.search.is_empty() {
+        title = format!(" Mail ({filter}, matching \"{}\") ", app.search);
+    }
+
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Fill(3),
+            Constraint::Length(8),
+            Constraint::Fill(1),
+            Constraint::Length(10),
+        ],
+    )
+    .header(Row::new(vec!["Name", "Type", "Status", "Created"]).bold())
+    .block(Block::bordered().title(title))
+    .row_highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, area, &mut app.table);
+}
+
+fn draw_details(frame: &mut Frame, app: &App, area: Rect) {
+    let Some(listed) = app.selected() else {
+        frame.render_widget(
+            Paragraph::new("Nothing selected").block(Block::bordered().title(" Details ")),
+            area,
+        );
+        return;
+    };
+    let loaded = listed.id.as_ref().and_then(|id| app.details.get(id));
+    let letter = loaded.unwrap_or(listed);
+
+    let field = |name: &'static str, value: Option<String>| {
+        Line::from(vec![
+            Span::from(format!("{name:<16}")).bold(),
+            Span::from(value.unwrap_or_else(|| String::from("-"))),
+        ])
+    };
+    let mut lines = vec![
+        field("ID", letter.id.clone()),
+        field("Name", Some(letter_name(letter))),
+        field("Type", letter.letter_type.clone()),
+        field("Subtype", letter.letter_subtype.clone()),
+        field("Status", letter.status.clone()),
+        field("Created At", letter.created_at.map(|date| date.to_string())),
+        field("Updated At", letter.updated_at.map(|date| date.to_string())),
+        field("Public URL", letter.public_url.clone()),
+        field("Tracking Number", letter.tracking_number.clone()),
+        field("Tracking Link", letter.tracking_link.clone()),
+        Line::from(""),
+    ];
+
+    match &letter.events {
+        Some(events) if !events.is_empty() => {
+            lines.push(Line::from("Events").bold().underlined());
+            for event in letter.timeline() {
+                let when = event
+                    .happened_at
+                    .map(|date| date.naive_utc().to_string())
+                    .unwrap_or_else(|| String::from("unknown time"));
+                lines.push(Line::from(vec![
+                    Span::from("● ").bold(),
+                    Span::from(when).dim(),
+                ]));
+                lines.push(Line::from(format!(
+                    "  {}",
+                    event.description.as_deref().unwrap_or("no description")
+                )));
+                if let Some(place) = event.place(" · ") {
+                    lines.push(Line::from(format!("  {place}")).dim());
+                }
+            }
+        }
+        Some(_) => lines.push(Line::from("No events yet").dim()),
+        None if loaded.is_some() => lines.push(Line::from("No events").dim()),
+        None => lines.push(Line::from("Loading events...").dim()),
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::bordered().title(" Details ")),
+        area,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.letters = serde_json::from_value(json!([
+            { "id": "ltr!abc123", "type": "letter", "title": "Shipwrecked stickers" },
+            { "id": "pkg!xyz789", "type": "package", "title": "Hoodie", "tags": ["swag"] },
+            { "id": "rec123", "type": "msr", "title": "Arcade prize" },
+            { "id": "rec456", "tags": ["arcade", "sticker"] }
+        ]))
+        .unwrap();
+        app
+    }
+
+    fn ids(app: &App) -> Vec<&str> {
+        app.visible()
+            .iter()
+            .filter_map(|letter| letter.id.as_deref())
+            .collect()
+    }
+
+    #[test]
+    fn filters_by_type_and_search() {
+        let mut app = app();
+        assert_eq!(ids(&app).len(), 4);
+
+        app.cycle_type();
+        assert_eq!(app.mail_type, Some(MailType::Letter));
+        assert_eq!(ids(&app), ["ltr!abc123"]);
+        app.cycle_type();
+        assert_eq!(ids(&app), ["pkg!xyz789"]);
+        app.cycle_type();
+        assert_eq!(ids(&app), ["rec123", "rec456"]);
+        app.cycle_type();
+        assert_eq!(app.mail_type, None);
+
+        // titles, tags and ids, ignoring case
+        app.search = String::from("HOODIE");
+        assert_eq!(ids(&app), ["pkg!xyz789"]);
+        app.search = String::from("sticker");
+        assert_eq!(ids(&app), ["ltr!abc123", "rec456"]);
+        app.search = String::from("rec1");
+        assert_eq!(ids(&app), ["rec123"]);
+        // tags count even when there's a title
+        app.search = String::from("Swag");
+        assert_eq!(ids(&app), ["pkg!xyz789"]);
+    }
+
+    #[test]
+    fn details_are_only_fetched_once() {
+        let mut app = app();
+        app.select(0);
+        assert_eq!(app.next_load(), Some((String::from("ltr!abc123"), None)));
+        // still being fetched
+        assert_eq!(app.next_load(), None);
+
+        app.loaded(String::from("ltr!abc123"), None, Err(MailError::NotFound));
+        assert_eq!(app.next_load(), None);
+        assert_eq!(
+            app.details["ltr!abc123"].title.as_deref(),
+            Some("Shipwrecked stickers")
+        );
+    }
+
+    #[test]
+    fn selection_stays_in_the_list() {
+        let mut app = app();
+        app.select(10);
+        assert_eq!(app.table.selected(), Some(3));
+        app.select(-10);
+        assert_eq!(app.table.selected(), Some(0));
+        app.select(1);
+        assert_eq!(app.selected().unwrap().id.as_deref(), Some("pkg!xyz789"));
+
+        // changing the type goes back to the top
+        app.cycle_type();
+        assert_eq!(app.table.selected(), Some(0));
+        app.search = String::from("nothing like this");
+        app.select(0);
+        assert_eq!(app.table.selected(), None);
+        assert!(app.selected().is_none());
+    }
+}
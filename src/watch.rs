@@ -0,0 +1,73 @@
+use crate::changes::{Change, diff};
+use crate::error::MailError;
+use crate::mail::{Letter, MailClient, MailType};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Keeps the last state of some mail in memory and reports what changed each time it's polled.
+#[derive(Default)]
+pub struct Watcher {
+    target: Option<(String, Option<MailType>)>,
+    known: HashMap<String, Letter>,
+    primed: bool,
+}
+
+impl Watcher {
+    /// Watches all of your mail.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// Watches a single piece of mail, looked up like [`MailClient::get_by_id`].
+    pub fn one(id: impl Into<String>, mail_type: Option<MailType>) -> Self {
+        Self {
+            target: Some((id.into(), mail_type)),
+            ..Default::default()
+        }
+    }
+
+    /// How many pieces of mail are being watched.
+    pub fn len(&self) -> usize {
+        self.known.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.known.is_empty()
+    }
+
+    /// Fetches the mail again and returns what changed since the last poll. The first poll only
+    /// records where things stand and never reports changes.
+    pub async fn poll(&mut self, client: &MailClient) -> Result<Vec<Change>, MailError> {
+        let letters = match &self.target {
+            Some((id, mail_type)) => vec![client.get_by_id(id, *mail_type).await?],
+            None => client.get_mail_with_events(None).await?.letters,
+        };
+        Ok(self.update(letters))
+    }
+
+    /// Records `letters` as the current state and returns what changed.
+    pub fn update(&mut self, letters: Vec<Letter>) -> Vec<Change> {
+        let mut changes = Vec::new();
+        for letter in letters {
+            let Some(id) = letter.id.clone() else {
+                continue;
+            };
+            if self.primed {
+                changes.extend(diff(self.known.get(&id), &letter));
+            }
+            self.known.insert(id, letter);
+        }
+        self.primed = true;
+        changes
+    }
+}
+
+/// How long to wait before polling again after `failures` failed polls in a row. Doubles from
+/// `interval` up to an hour, or stays at `interval` if that's already longer.
+pub fn backoff(interval: Duration, failures: u32) -> Duration {
+    let cap = interval.max(Duration::from_secs(60 * 60));
+    interval
+        .checked_mul(2u32.saturating_pow(failures))
+        .unwrap_or(cap)
+        .min(cap)
+}
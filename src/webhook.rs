@@ -0,0 +1,112 @@
+use crate::changes::Change;
+use crate::error::WebhookError;
+use crate::mail::DEFAULT_TIMEOUT;
+use crate::retry::Retry;
+use hmac::{Hmac, Mac};
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+
+/// Header with the kind of change, e.g. `new_event`.
+pub const EVENT_HEADER: &str = "X-Orphy-Event";
+/// Header with the signature of the body, `sha256=` followed by the hex HMAC-SHA256 of the body
+/// keyed with the secret. Only sent when there's a secret.
+pub const SIGNATURE_HEADER: &str = "X-Orphy-Signature-256";
+
+/// An http endpoint that gets a POST for every change to your mail. The body is the change as
+/// json, the same as `orphy changes --format json` prints for it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Webhook {
+    pub url: String,
+    /// Signs every body so the receiver can check it came from you, see [`SIGNATURE_HEADER`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+    /// How many times to retry a delivery that failed on a blip (connection problems, 5xx and
+    /// 429 answers), waiting longer each time. Defaults to [`Retry::default`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+}
+
+impl Webhook {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// The retry policy deliveries use.
+    pub fn retry(&self) -> Retry {
+        Retry {
+            max_retries: self.retries.unwrap_or(Retry::default().max_retries),
+            ..Retry::default()
+        }
+    }
+
+    /// The value of [`SIGNATURE_HEADER`] for `body`, if there's a secret.
+    pub fn signature(&self, body: &[u8]) -> Option<String> {
+        let secret = self.secret.as_ref()?;
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac takes keys of any size");
+        mac.update(body);
+        let hex: String = mac
+            .finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Some(format!("sha256={hex}"))
+    }
+
+    /// POSTs `change`, retrying failures that might go away on their own.
+    pub async fn send(&self, client: &Client, change: &Change) -> Result<(), WebhookError> {
+        let body = serde_json::to_vec(change).expect("changes always serialize");
+        let retry = self.retry();
+        let mut attempt = 0;
+        loop {
+            let mut request = client
+                .post(&self.url)
+                .header(CONTENT_TYPE, "application/json")
+                .header(EVENT_HEADER, change.kind())
+                .timeout(DEFAULT_TIMEOUT)
+                .body(body.clone());
+            if let Some(signature) = self.signature(&body) {
+                request = request.header(SIGNATURE_HEADER, signature);
+            }
+
+            let err = match request.send().await {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    let err = WebhookError::Status {
+                        url: self.url.clone(),
+                        status,
+                    };
+                    if !status.is_server_error() && status != StatusCode::TOO_MANY_REQUESTS {
+                        return Err(err);
+                    }
+                    err
+                }
+                Err(source) => {
+                    let transient =
+                        source.is_connect() || source.is_timeout() || source.is_request();
+                    let err = WebhookError::Network {
+                        url: self.url.clone(),
+                        source,
+                    };
+                    if !transient {
+                        return Err(err);
+                    }
+                    err
+                }
+            };
+            attempt += 1;
+            if attempt > retry.max_retries {
+                return Err(err);
+            }
+            tokio::time::sleep(retry.delay(attempt)).await;
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use orphy::{Carrier, Letter};
+use serde_json::json;
+
+#[test]
+fn recognizes_each_carrier() {
+    for (number, carrier) in [
+        ("1Z999AA10123456784", Carrier::Ups),
+        ("9400100000000000000006", Carrier::Usps),
+        ("9400111899223456789017", Carrier::Usps),
+        ("420054019400100000000000000006", Carrier::Usps),
+        ("RA123456785US", Carrier::Usps),
+        ("986578788855", Carrier::Fedex),
+        ("041441760228964", Carrier::Fedex),
+        ("9611020987654312345672", Carrier::Fedex),
+        ("1234567891", Carrier::Dhl),
+        ("JJD0099999999", Carrier::Dhl),
+        ("GM2951173225174494", Carrier::Dhl),
+        ("7023210039414604", Carrier::CanadaPost),
+        ("EE123456785CA", Carrier::CanadaPost),
+        ("RR123456785GB", Carrier::RoyalMail),
+        ("ASUS000123456789", Carrier::Asendia),
+    ] {
+        assert_eq!(Carrier::detect(number), Some(carrier), "{number}");
+    }
+}
+
+#[test]
+fn wrong_check_digits_arent_recognized() {
+    for number in [
+        "1Z999AA10123456785",
+        "9400111899223456789012",
+        "RA123456786US",
+        "986578788856",
+        "041441760228965",
+        "9611020987654312345673",
+        "1234567892",
+    ] {
+        assert_eq!(Carrier::detect(number), None, "{number}");
+    }
+    // a valid S10 number from a post orphy doesn't know
+    assert_eq!(Carrier::detect("RA123456785DE"), None);
+    assert_eq!(Carrier::detect("not a number"), None);
+    assert_eq!(Carrier::detect(""), None);
+}
+
+#[test]
+fn spaces_dashes_and_case_dont_matter() {
+    assert_eq!(
+        Carrier::detect("1z 999 aa1 0123 4567 84"),
+        Some(Carrier::Ups)
+    );
+    assert_eq!(Carrier::detect("RA-123-456-785-US"), Some(Carrier::Usps));
+    assert_eq!(
+        Carrier::Ups.tracking_url("1z 999 aa1 0123 4567 84"),
+        "https://www.ups.com/track?tracknum=1Z999AA10123456784"
+    );
+    assert_eq!(Carrier::CanadaPost.to_string(), "Canada Post");
+}
+
+#[test]
+fn carriers_from_tracking_links() {
+    assert_eq!(
+        Carrier::from_link("https://tools.usps.com/go/TrackConfirmAction?tLabels=9400"),
+        Some(Carrier::Usps)
+    );
+    assert_eq!(
+        Carrier::from_link("https://www.royalmail.com/track-your-item"),
+        Some(Carrier::RoyalMail)
+    );
+    assert_eq!(Carrier::from_link("https://notups.com/track"), None);
+    assert_eq!(Carrier::from_link("not a link"), None);
+}
+
+#[test]
+fn letters_fall_back_to_the_carriers_tracking_page() {
+    let legacy: Letter = serde_json::from_value(json!({
+        "id": "rec123", "tracking_number": "1Z999AA10123456784"
+    }))
+    .unwrap();
+    assert_eq!(legacy.carrier(), Some(Carrier::Ups));
+    assert_eq!(
+        legacy.tracking_url().as_deref(),
+        Some("https://www.ups.com/track?tracknum=1Z999AA10123456784")
+    );
+
+    // the api's own link wins, and says who the carrier is when the number doesn't
+    let package: Letter = serde_json::from_value(json!({
+        "id": "pkg!xyz789",
+        "tracking_number": "9400111899223456789012",
+        "tracking_link": "https://tools.usps.com/go/TrackConfirmAction?tLabels=9400111899223456789012"
+    }))
+    .unwrap();
+    assert_eq!(package.carrier(), Some(Carrier::Usps));
+    assert_eq!(package.tracking_url(), package.tracking_link);
+
+    let unknown: Letter = serde_json::from_value(json!({ "tracking_number": "12345" })).unwrap();
+    assert_eq!(unknown.carrier(), None);
+    assert_eq!(unknown.tracking_url(), None);
+}
@@ -0,0 +1,984 @@
+mod common;
+
+use common::{
+    DOWN_TOKEN, EXPIRED_TOKEN, FLAKY_TOKEN, MockServer, Orphy, TOKEN, WebhookReceiver, stderr,
+    stdout,
+};
+use orphy::Cache;
+use serde_json::Value;
+
+#[test]
+fn setup_saves_the_api_key() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+
+    let output = orphy.run(&["setup", TOKEN]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("Saved your api key!"));
+
+    let config = std::fs::read_to_string(
+        orphy
+            .config_dir
+            .path()
+            .join("orphy_hackclub_mail_client/default-config.toml"),
+    )
+    .unwrap();
+    assert!(config.contains(TOKEN));
+}
+
+#[test]
+fn errors_say_what_caused_them() {
+    let server = MockServer::start();
+    let output = Orphy::new(&server).run(&[
+        "setup",
+        TOKEN,
+        "--instance",
+        "http://127.0.0.1:9",
+        "--retries",
+        "0",
+    ]);
+
+    assert_eq!(output.status.code(), Some(16));
+    let stderr = stderr(&output);
+    assert!(
+        stderr.starts_with("Couldn't verify your api key"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("\ncaused by: Couldn't reach Hack Club Mail"));
+    assert_eq!(stderr.matches("error sending request").count(), 1);
+}
+
+#[test]
+fn setup_refuses_a_key_the_server_rejects() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+
+    let output = orphy.run(&["setup", EXPIRED_TOKEN]);
+    assert_eq!(output.status.code(), Some(10));
+    assert!(stderr(&output).contains("--no-verify"));
+    let config = std::fs::read_to_string(
+        orphy
+            .config_dir
+            .path()
+            .join("orphy_hackclub_mail_client/default-config.toml"),
+    )
+    .unwrap();
+    assert!(!config.contains(EXPIRED_TOKEN));
+}
+
+#[test]
+fn setup_can_encrypt_the_api_key() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+
+    let output = orphy
+        .command(&["setup", TOKEN, "--encrypt"])
+        .env("ORPHY_PASSPHRASE", "correct horse")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let config = std::fs::read_to_string(
+        orphy
+            .config_dir
+            .path()
+            .join("orphy_hackclub_mail_client/default-config.toml"),
+    )
+    .unwrap();
+    assert!(config.contains("encrypted_key"));
+    assert!(!config.contains(TOKEN));
+
+    let output = orphy
+        .command(&["mail"])
+        .env("ORPHY_PASSPHRASE", "correct horse")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = orphy
+        .command(&["mail"])
+        .env("ORPHY_PASSPHRASE", "battery staple")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    assert!(stderr(&output).contains("passphrase"));
+}
+
+#[cfg(unix)]
+#[test]
+fn setup_can_use_a_token_command() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+    let command = format!("printf %s {TOKEN}");
+
+    let output = orphy.run(&["setup", "--token-command", &command]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(orphy.run(&["mail"]).status.success());
+    assert!(server.requests().iter().all(|path| !path.contains(TOKEN)));
+}
+
+#[test]
+fn the_api_key_can_come_from_the_environment() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, EXPIRED_TOKEN);
+
+    let output = orphy
+        .command(&["mail"])
+        .env("ORPHY_API_KEY", TOKEN)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn mail_without_an_api_key_asks_for_one() {
+    let server = MockServer::start();
+    let output = Orphy::new(&server).run(&["mail"]);
+    assert_eq!(output.status.code(), Some(22));
+    assert!(stderr(&output).contains("orphy setup"));
+    assert!(stdout(&output).is_empty());
+    assert!(server.requests().is_empty());
+}
+
+#[test]
+fn an_unreadable_config_is_an_error() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+    let config = orphy.config_dir.path().join("orphy_hackclub_mail_client");
+    std::fs::create_dir_all(&config).unwrap();
+    std::fs::write(config.join("default-config.toml"), "api_key = [").unwrap();
+
+    let output = orphy.run(&["mail"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("Couldn't read orphy's config file"));
+}
+
+#[test]
+fn mail_prints_a_table_and_warns_about_bad_records() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail"]);
+
+    assert!(output.status.success());
+    let table = stdout(&output);
+    assert!(table.contains("Shipwrecked stickers"));
+    assert!(table.contains("pkg!xyz789"));
+    assert!(table.contains("letter: orpheus, 3"));
+    assert!(stderr(&output).contains("ltr!broken"));
+}
+
+#[test]
+fn mail_filters_by_type() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail", "--type", "package"]);
+
+    assert!(stdout(&output).contains("Hoodie"));
+    assert!(!stdout(&output).contains("Shipwrecked stickers"));
+}
+
+#[test]
+fn mail_as_json() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail", "--format", "json"]);
+
+    let letters: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let letters = letters.as_array().unwrap();
+    assert_eq!(letters.len(), 4);
+    assert_eq!(letters[0]["id"], "ltr!abc123");
+    assert_eq!(letters[0]["type"], "letter");
+    assert_eq!(letters[0]["created_at"], "2025-06-01T16:00:00Z");
+    assert_eq!(letters[2]["created_at"], Value::Null);
+    // the api's own carrier field doesn't end up next to the one orphy works out
+    assert_eq!(letters[1]["carrier"], "usps");
+    assert_eq!(
+        letters[1]["tracking_url"],
+        "https://tools.usps.com/go/TrackConfirmAction?tLabels=9400111899223456789012"
+    );
+    assert_eq!(stdout(&output).matches("\"carrier\"").count(), 4);
+}
+
+#[test]
+fn mail_as_ndjson() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail", "-f", "ndjson"]);
+
+    let lines: Vec<Value> = stdout(&output)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[1]["id"], "pkg!xyz789");
+}
+
+#[test]
+fn mail_as_csv() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail", "--format", "csv"]);
+
+    let csv = stdout(&output);
+    let mut lines = csv.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "id,title,type,subtype,status,tags,created_at,updated_at,public_url,tracking_number,tracking_link,carrier,tracking_url,path"
+    );
+    assert!(
+        lines.next().unwrap().starts_with(
+            "ltr!abc123,Shipwrecked stickers,letter,flat,mailed,shipwrecked;stickers,"
+        )
+    );
+    assert_eq!(lines.count(), 3);
+}
+
+#[test]
+fn mail_as_yaml() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["mail", "--format", "yaml"]);
+
+    let letters: Value = serde_yaml::from_slice(&output.stdout).unwrap();
+    assert_eq!(letters[3]["id"], "ltr!bare");
+}
+
+#[test]
+fn view_shows_details_and_events() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["view", "--id", "ltr!abc123"]);
+
+    assert!(output.status.success());
+    let view = stdout(&output);
+    assert!(view.contains("Shipwrecked stickers"));
+    assert!(view.contains("Events"));
+    assert!(view.contains("Burlington VT"));
+    assert_eq!(server.requests(), ["/api/public/v1/letters/ltr!abc123"]);
+}
+
+#[test]
+fn view_shows_events_as_a_timeline() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["view", "--id", "ltr!abc123"]);
+
+    let view = stdout(&output);
+    let mailed = view.find("● Mailed").unwrap();
+    let processed = view.find("● Processed through facility").unwrap();
+    assert!(mailed < processed);
+    assert!(view.contains("20 hours 15 minutes later"));
+    assert!(view.contains("Burlington VT · Burlington, VT 05401"));
+    assert!(view.contains("days ago"));
+}
+
+#[test]
+fn view_events_only_leaves_the_details_out() {
+    let server = MockServer::start();
+    let output =
+        Orphy::with_token(&server, TOKEN).run(&["view", "--id", "pkg!xyz789", "--events-only"]);
+
+    let view = stdout(&output);
+    assert!(view.contains("Hoodie (pkg!xyz789)"));
+    assert!(!view.contains("Tracking Number"));
+    // events without a time go last
+    let created = view.find("● Shipping label created").unwrap();
+    let in_transit = view.find("● In transit").unwrap();
+    assert!(created < in_transit);
+    assert!(view.contains("unknown time"));
+}
+
+#[test]
+fn view_latest_event_is_one_line_each() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&[
+        "view",
+        "--id",
+        "ltr!abc123",
+        "--id",
+        "pkg!xyz789",
+        "--latest-event",
+    ]);
+
+    let view = stdout(&output);
+    assert!(view.contains(
+        "ltr!abc123 Shipwrecked stickers: Processed through facility at Burlington VT ("
+    ));
+    assert!(view.contains("pkg!xyz789 Hoodie: Shipping label created at Shelburne VT"));
+
+    let output = Orphy::with_token(&server, TOKEN).run(&[
+        "view",
+        "--id",
+        "ltr!abc123",
+        "--latest-event",
+        "-f",
+        "json",
+    ]);
+    let latest: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(latest["id"], "ltr!abc123");
+    assert_eq!(
+        latest["latest_event"]["description"],
+        "Processed through facility"
+    );
+}
+
+#[test]
+fn view_with_an_explicit_type() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&[
+        "view",
+        "--id",
+        "msr/rec123",
+        "--type",
+        "legacy",
+        "--format",
+        "json",
+    ]);
+
+    let record: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["title"], "Arcade prize");
+}
+
+#[test]
+fn view_links_to_the_carrier_when_the_api_doesnt() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["view", "--id", "msr/rec123"]);
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    let view = stdout(&output);
+    assert!(view.contains("Carrier"));
+    assert!(view.contains("UPS"));
+    assert!(view.contains("https://www.ups.com/track?tracknum=1Z999AA10123456784"));
+
+    let output =
+        Orphy::with_token(&server, TOKEN).run(&["view", "--id", "msr/rec123", "-f", "json"]);
+    let view: Value = serde_json::from_str(&stdout(&output)).unwrap();
+    assert_eq!(view["carrier"], "ups");
+    assert_eq!(
+        view["tracking_url"],
+        "https://www.ups.com/track?tracknum=1Z999AA10123456784"
+    );
+    assert_eq!(view["tracking_link"], Value::Null);
+
+    let output =
+        Orphy::with_token(&server, TOKEN).run(&["view", "--id", "msr/rec123", "-f", "csv"]);
+    assert!(
+        stdout(&output).contains(",ups,https://www.ups.com/track?tracknum=1Z999AA10123456784,")
+    );
+}
+
+#[test]
+fn view_as_csv_has_a_row_per_event() {
+    let server = MockServer::start();
+    let output =
+        Orphy::with_token(&server, TOKEN).run(&["view", "--id", "pkg!xyz789", "-f", "csv"]);
+
+    let csv = stdout(&output);
+    assert_eq!(csv.lines().count(), 3);
+    assert!(csv.contains("Shipping label created"));
+}
+
+#[test]
+fn view_of_missing_mail_exits_with_not_found() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["view", "--id", "ltr!nothere"]);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert!(stderr(&output).contains("404"));
+}
+
+#[test]
+fn fetch_prints_counts() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["fetch"]);
+
+    let banner = stdout(&output);
+    assert!(banner.contains("id: usr!test"));
+    assert!(banner.contains(&format!("instance: {}", server.url)));
+    assert!(banner.contains("mail: 4"));
+}
+
+#[test]
+fn fetch_as_json() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&["fetch", "--format", "json"]);
+
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["id"], "usr!test");
+    assert_eq!(summary["mail"], 4);
+    assert_eq!(summary["letters"], 1);
+    assert_eq!(summary["packages"], 1);
+    assert_eq!(summary["legacy"], 2);
+}
+
+#[test]
+fn stats_work_out_transit_times() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let output = orphy.run(&["stats", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let stats: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats["mail"], 4);
+    assert_eq!(stats["statuses"]["mailed"], 1);
+    // the letter was created at the same time as its first event
+    assert_eq!(stats["to_first_event"]["fastest_seconds"], 0);
+    assert_eq!(stats["facilities"][0]["facility"], "Burlington VT");
+    assert_eq!(stats["months"]["2025-06"], 1);
+
+    let table = stdout(&orphy.run(&["stats"]));
+    assert!(table.contains("Stats for 4 pieces of mail"));
+    assert!(table.contains("Created to first scan"));
+    assert!(table.contains("Shelburne VT"));
+}
+
+#[test]
+fn export_ics_writes_a_calendar() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let path = orphy.config_dir.path().join("mail.ics");
+
+    let output = orphy.run(&["export", "ics", "--output", path.to_str().unwrap()]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("Saved the calendar"));
+    let calendar = std::fs::read_to_string(path).unwrap();
+    assert!(calendar.contains("UID:ltr!abc123-created@orphy"));
+    assert!(calendar.contains("SUMMARY:Hoodie: Shipping label created"));
+
+    let output = orphy.run(&["export", "ics", "--type", "package"]);
+    let calendar = stdout(&output);
+    assert!(calendar.starts_with("BEGIN:VCALENDAR"));
+    assert!(!calendar.contains("Shipwrecked stickers"));
+}
+
+#[test]
+fn export_feed_as_atom_or_rss() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let atom = stdout(&orphy.run(&["export", "feed"]));
+    assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    assert!(atom.contains("<id>urn:orphy:ltr!abc123</id>"));
+    assert!(atom.contains(&format!("<link href=\"{}\"/>", server.url)));
+
+    let rss = stdout(&orphy.run(&["export", "feed", "--format", "rss"]));
+    assert!(rss.contains("<guid isPermaLink=\"false\">urn:orphy:pkg!xyz789</guid>"));
+
+    let output = orphy.run(&["export", "feed", "--format", "json"]);
+    assert!(!output.status.success());
+    let output = orphy.run(&["mail", "--format", "rss"]);
+    assert!(stderr(&output).contains("only work with orphy export feed"));
+}
+
+#[test]
+fn rejected_token_exits_with_its_own_code() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, EXPIRED_TOKEN).run(&["mail"]);
+
+    assert_eq!(output.status.code(), Some(10));
+    assert!(stderr(&output).contains("401"));
+}
+
+#[test]
+fn server_errors_exit_with_their_own_code() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, DOWN_TOKEN).run(&["fetch", "--retries", "0"]);
+
+    assert_eq!(output.status.code(), Some(14));
+}
+
+#[test]
+fn credit() {
+    let server = MockServer::start();
+    let output = Orphy::new(&server).run(&["credit"]);
+    assert!(stdout(&output).contains("ErrorCode0"));
+}
+
+#[test]
+fn changes_are_posted_to_webhooks() {
+    let server = MockServer::start();
+    let receiver = WebhookReceiver::start(&[200]);
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let path = orphy
+        .config_dir
+        .path()
+        .join("orphy_hackclub_mail_client/default-config.toml");
+    let mut config = std::fs::read_to_string(&path).unwrap();
+    config += &format!(
+        "\n[[webhook]]\nurl = \"{}\"\nsecret = \"hunter2\"\n",
+        receiver.url
+    );
+    std::fs::write(&path, config).unwrap();
+
+    let output = orphy.run(&["changes", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let changes: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let deliveries = receiver.deliveries();
+    assert_eq!(deliveries.len(), changes.as_array().unwrap().len());
+    assert!(deliveries[0].body.contains("\"kind\":\"new_letter\""));
+    assert!(
+        deliveries[0]
+            .header("x-orphy-signature-256")
+            .unwrap()
+            .starts_with("sha256=")
+    );
+
+    // nothing changed, so nothing is posted
+    orphy.run(&["changes"]);
+    assert_eq!(receiver.deliveries().len(), deliveries.len());
+}
+
+#[test]
+fn changes_reports_new_mail_then_nothing() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let output = orphy.run(&["changes", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let changes: Value = serde_json::from_slice(&output.stdout).unwrap();
+    let changes = changes.as_array().unwrap();
+    assert_eq!(changes.len(), 4);
+    assert!(changes.iter().all(|change| change["kind"] == "new_letter"));
+    assert_eq!(changes[0]["letter"]["events"].as_array().unwrap().len(), 2);
+
+    let output = orphy.run(&["changes"]);
+    assert!(stdout(&output).contains("Nothing changed since"));
+    assert!(
+        orphy
+            .config_dir
+            .path()
+            .join("orphy_hackclub_mail_client/history.sqlite3")
+            .exists()
+    );
+}
+
+#[test]
+fn changes_doesnt_fall_back_on_the_cache() {
+    use sha2::{Digest, Sha256};
+
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, DOWN_TOKEN);
+    let key: String = Sha256::digest(DOWN_TOKEN.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let cache = Cache::new(
+        orphy
+            .config_dir
+            .path()
+            .join("orphy_hackclub_mail_client/cache/default")
+            .join(key),
+    );
+    for (path, fixture) in [
+        ("mail", "mail"),
+        ("letters/ltr!abc123", "letter"),
+        ("packages/pkg!xyz789", "package"),
+        ("lsv/msr/rec123", "lsv_record"),
+    ] {
+        let body: Value = serde_json::from_str(&common::fixture_json(fixture)).unwrap();
+        cache
+            .put(&format!("{}/api/public/v1/{path}", server.url), &body)
+            .unwrap();
+    }
+
+    let output = orphy.run(&["mail", "--retries", "0"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("cached at"));
+
+    let output = orphy.run(&["changes", "--retries", "0"]);
+    assert_eq!(output.status.code(), Some(14), "{}", stderr(&output));
+    assert!(!stdout(&output).contains("New"));
+}
+
+#[cfg(unix)]
+#[test]
+fn watch_stops_cleanly_on_ctrl_c() {
+    use std::process::Stdio;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let child = orphy
+        .command(&["watch", "--id", "ltr!abc123", "--interval", "200ms"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    sleep(Duration::from_millis(1000));
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Watching 1 pieces of mail"));
+    assert!(server.requests().len() >= 2);
+}
+
+#[cfg(unix)]
+#[test]
+fn serve_shares_the_mail_as_json_and_html() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let (child, url) = start_server(&orphy, &["serve", "--bind", "127.0.0.1:0"]);
+    let get = |path: &str| reqwest::blocking::get(format!("{url}{path}")).unwrap();
+    let json = |path: &str| serde_json::from_str::<Value>(&get(path).text().unwrap()).unwrap();
+
+    let mail = json("/api/public/v1/mail");
+    assert_eq!(mail["mail"].as_array().unwrap().len(), 4);
+    let packages = json("/api/public/v1/packages");
+    assert_eq!(packages["packages"][0]["id"], "pkg!xyz789");
+    let letter = json("/api/public/v1/letters/ltr!abc123");
+    assert_eq!(letter["letter"]["events"].as_array().unwrap().len(), 2);
+    let legacy = json("/api/public/v1/lsv/msr/rec123");
+    assert_eq!(
+        legacy["legacy_shipment_viewer_record"]["title"],
+        "Arcade prize"
+    );
+    assert_eq!(get("/api/public/v1/letters/ltr!nope").status(), 404);
+
+    let index = get("/").text().unwrap();
+    assert!(index.contains("<a href=\"/mail/ltr%21abc123\">Shipwrecked stickers</a>"));
+    let details = get("/mail/ltr!abc123").text().unwrap();
+    let mailed = details.find("Mailed").unwrap();
+    assert!(mailed < details.find("Processed through facility").unwrap());
+    assert_eq!(get("/mail/ltr!nope").status(), 404);
+
+    stop_server(child);
+}
+
+#[cfg(unix)]
+#[test]
+fn serve_refreshes_from_hack_club_mail_even_with_max_age() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let (child, url) = start_server(
+        &orphy,
+        &[
+            "serve",
+            "--bind",
+            "127.0.0.1:0",
+            "--interval",
+            "50ms",
+            "--max-age",
+            "1h",
+        ],
+    );
+    let first = server.requests().len();
+
+    let refreshed = (0..100).any(|_| {
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        server.requests().len() > first
+    });
+    assert!(refreshed);
+    let index = reqwest::blocking::get(&url).unwrap().text().unwrap();
+    assert!(!index.contains("the last refresh failed"));
+    stop_server(child);
+}
+
+#[cfg(unix)]
+#[test]
+fn exporter_serves_prometheus_metrics() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, FLAKY_TOKEN);
+    let (child, url) = start_server(&orphy, &["exporter", "--bind", "127.0.0.1:0"]);
+    assert!(url.ends_with("/metrics"));
+
+    let response = reqwest::blocking::get(&url).unwrap();
+    assert!(
+        response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain; version=0.0.4")
+    );
+    let metrics = response.text().unwrap();
+    assert!(metrics.contains("orphy_mail{type=\"package\",status=\"shipped\"} 1\n"));
+    assert!(metrics.contains("orphy_last_event_timestamp_seconds "));
+    // the list and three details, each answered with a 503 the first time
+    assert!(metrics.contains("orphy_api_requests_total 8\n"));
+    assert!(metrics.contains("orphy_api_request_errors_total{kind=\"server\"} 4\n"));
+    assert!(metrics.contains("orphy_api_request_duration_seconds_count 8\n"));
+    assert!(metrics.contains("orphy_last_refresh_timestamp_seconds "));
+    assert!(metrics.contains("orphy_refresh_errors_total 0\n"));
+    stop_server(child);
+}
+
+/// Starts `orphy serve` or `orphy exporter` and gives back where it's serving, from the line it
+/// prints on stderr once it's listening.
+#[cfg(unix)]
+fn start_server(orphy: &Orphy, args: &[&str]) -> (std::process::Child, String) {
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let mut child = orphy
+        .command(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(child.stderr.take().unwrap()).lines();
+    let url = loop {
+        let line = lines.next().unwrap().unwrap();
+        if let Some((_, rest)) = line.split_once(" on http://") {
+            break format!("http://{}", rest.split_once(". ").unwrap().0);
+        }
+    };
+    // keep reading so anything printed later doesn't fail on a closed pipe
+    std::thread::spawn(move || lines.for_each(drop));
+    (child, url)
+}
+
+/// Stops a server from [`start_server`] with Ctrl-C and checks it exits cleanly.
+#[cfg(unix)]
+fn stop_server(mut child: std::process::Child) {
+    std::process::Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn mail_filters_sorts_and_limits() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let ids = |args: &[&str]| {
+        let output = orphy.run(&[&["mail", "--format", "json"], args].concat());
+        assert!(output.status.success(), "{}", stderr(&output));
+        let letters: Value = serde_json::from_slice(&output.stdout).unwrap();
+        letters
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|letter| letter["id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(ids(&["--status", "SHIPPED"]), ["pkg!xyz789"]);
+    assert_eq!(ids(&["--tag", "stickers"]), ["ltr!abc123"]);
+    assert_eq!(ids(&["--search", "orpheus"]), ["ltr!bare"]);
+    assert_eq!(ids(&["--until", "2025-05-20"]), ["pkg!xyz789"]);
+    assert_eq!(ids(&["--since", "2025-05-21"]), ["ltr!abc123"]);
+    assert_eq!(
+        ids(&["--sort", "created", "--reverse", "--limit", "2"]),
+        ["ltr!bare", "rec123"]
+    );
+    assert_eq!(ids(&["--sort", "title", "--limit", "1"]), ["rec123"]);
+}
+
+#[test]
+fn an_old_config_becomes_the_default_profile() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+    let dir = orphy.config_dir.path().join("orphy_hackclub_mail_client");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("default-config.toml"),
+        format!("api_key = \"{TOKEN}\"\n"),
+    )
+    .unwrap();
+
+    let output = orphy.run(&["fetch", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["profile"], "default");
+    assert_eq!(summary["mail"], 4);
+}
+
+#[test]
+fn notify_actions_survive_saving_the_config() {
+    let server = MockServer::start();
+    let orphy = Orphy::new(&server);
+    let dir = orphy.config_dir.path().join("orphy_hackclub_mail_client");
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("default-config.toml"),
+        "[[notify]]\ncommand = \"notify-send \\\"$ORPHY_TITLE\\\"\"\n\n[[notify]]\nbell = true\n",
+    )
+    .unwrap();
+
+    let output = orphy.run(&["profile", "add", "club", TOKEN, "--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let config = std::fs::read_to_string(dir.join("default-config.toml")).unwrap();
+    assert!(
+        config.contains("command = 'notify-send \"$ORPHY_TITLE\"'"),
+        "{config}"
+    );
+    assert!(config.contains("bell = true"));
+    assert!(config.contains("[profiles.club]"));
+}
+
+#[test]
+fn profiles_can_be_added_used_and_removed() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let output = orphy.run(&["profile", "add", "club", EXPIRED_TOKEN, "--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    assert!(orphy.run(&["mail"]).status.success());
+    assert_eq!(
+        orphy.run(&["--profile", "club", "mail"]).status.code(),
+        Some(10)
+    );
+
+    assert!(orphy.run(&["profile", "use", "club"]).status.success());
+    assert_eq!(orphy.run(&["mail"]).status.code(), Some(10));
+
+    let output = orphy.run(&["profile", "list", "--format", "json"]);
+    let profiles: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(profiles[0]["name"], "club");
+    assert_eq!(profiles[0]["active"], true);
+    assert_eq!(profiles[1]["name"], "default");
+    assert!(!stdout(&output).contains(TOKEN));
+
+    // its history and cached mail go with it, the other profiles' stay
+    let dir = orphy.config_dir.path().join("orphy_hackclub_mail_client");
+    std::fs::write(dir.join("history-club.sqlite3"), "").unwrap();
+    std::fs::create_dir_all(dir.join("cache/club/key")).unwrap();
+    assert!(orphy.run(&["profile", "remove", "club"]).status.success());
+    assert!(!dir.join("history-club.sqlite3").exists());
+    assert!(!dir.join("cache/club").exists());
+    assert!(dir.join("cache/default").exists());
+    assert!(orphy.run(&["mail"]).status.success());
+
+    let output = orphy.run(&["--profile", "club", "mail"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stderr(&output).contains("no profile named club"));
+
+    let output = orphy.run(&["profile", "add", "club", EXPIRED_TOKEN, "--no-verify"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    std::fs::write(dir.join("history-club.sqlite3"), "").unwrap();
+    let output = orphy.run(&["profile", "remove", "club", "--keep-data"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(dir.join("history-club.sqlite3").exists());
+}
+
+#[test]
+fn fetch_adds_up_every_profile() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    orphy.run(&["profile", "add", "club", TOKEN]);
+    orphy.run(&["profile", "add", "old", EXPIRED_TOKEN, "--no-verify"]);
+
+    let output = orphy.run(&["fetch", "--all-profiles", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Skipping profile old"));
+    let fetched: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(fetched["total"]["profiles"], 2);
+    assert_eq!(fetched["total"]["mail"], 8);
+    assert_eq!(fetched["profiles"][0]["profile"], "club");
+    assert_eq!(fetched["profiles"][1]["profile"], "default");
+}
+
+#[test]
+fn instance_flag_wins_over_the_environment() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let output = orphy
+        .command(&["fetch", "--format", "json", "--instance", &server.url])
+        .env("ORPHY_INSTANCE", "http://127.0.0.1:1")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let summary: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["instance"], server.url);
+
+    let output = orphy.run(&["mail", "--instance", "mail.hackclub.com"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stderr(&output).contains("http://"));
+
+    let output = orphy
+        .command(&["mail"])
+        .env("ORPHY_INSTANCE", "not a url")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(20));
+}
+
+#[test]
+fn offline_shows_what_was_cached() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+
+    let output = orphy.run(&["mail", "--offline"]);
+    assert_eq!(output.status.code(), Some(21));
+
+    assert!(orphy.run(&["mail"]).status.success());
+    let requests = server.requests().len();
+    let output = orphy.run(&["mail", "--offline", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(server.requests().len(), requests);
+    assert!(stderr(&output).contains("cached at"));
+    let letters: Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(letters.as_array().unwrap().len(), 4);
+
+    // another account's key doesn't get to see it
+    let output = orphy
+        .command(&["mail", "--offline"])
+        .env("ORPHY_API_KEY", DOWN_TOKEN)
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(21));
+}
+
+#[test]
+fn view_reports_on_many_ids_at_once() {
+    let server = MockServer::start();
+    let orphy = Orphy::with_token(&server, TOKEN);
+    let ids = |output: &std::process::Output| {
+        let letters: Value = serde_json::from_slice(&output.stdout).unwrap();
+        letters
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|letter| letter["id"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>()
+    };
+
+    let output = orphy.run(&[
+        "view",
+        "--id",
+        "pkg!xyz789",
+        "--id",
+        "ltr!abc123",
+        "--format",
+        "json",
+    ]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(ids(&output), ["pkg!xyz789", "ltr!abc123"]);
+
+    let output = orphy.run(&["view", "--all", "--type", "letter", "--format", "json"]);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(ids(&output), ["ltr!abc123"]);
+
+    let mut child = orphy
+        .command(&["view", "--stdin", "--format", "json"])
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+    std::io::Write::write_all(
+        child.stdin.as_mut().unwrap(),
+        b"ltr!abc123\nltr!nothere msr/rec123\n",
+    )
+    .unwrap();
+    drop(child.stdin.take());
+    let output = child.wait_with_output().unwrap();
+    assert_eq!(output.status.code(), Some(12));
+    assert!(stderr(&output).contains("Couldn't load ltr!nothere"));
+    assert!(stderr(&output).contains("Couldn't load 1 of 3 pieces of mail"));
+    assert_eq!(ids(&output), ["ltr!abc123", "rec123"]);
+}
+
+#[test]
+fn view_of_only_missing_mail_just_lists_the_errors() {
+    let server = MockServer::start();
+    let output = Orphy::with_token(&server, TOKEN).run(&[
+        "view",
+        "--id",
+        "ltr!nothere",
+        "--id",
+        "ltr!nowhere",
+    ]);
+
+    assert_eq!(output.status.code(), Some(12));
+    assert!(stderr(&output).contains("Couldn't load ltr!nowhere"));
+    assert!(!stdout(&output).contains("You don't have any mail"));
+}
@@ -0,0 +1,474 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::{
+    DOWN_TOKEN, EMPTY_TOKEN, EXPIRED_TOKEN, FLAKY_TOKEN, GARBAGE_TOKEN, LIMITED_TOKEN, MockServer,
+    SLOW_TOKEN, TOKEN,
+};
+use orphy::{Cache, Instance, MailClient, MailError, MailType, Retry};
+use std::time::{Duration, Instant};
+
+// errors are asserted on directly, retrying them would only slow the tests down
+fn client(server: &MockServer, token: &str) -> MailClient {
+    MailClient::builder(token)
+        .base_url(&server.url)
+        .retry(Retry::none())
+        .build()
+        .unwrap()
+}
+
+fn quick_retries() -> Retry {
+    Retry {
+        max_retries: 2,
+        base_delay: Duration::from_millis(10),
+        max_delay: Duration::from_secs(2),
+    }
+}
+
+#[tokio::test]
+async fn gets_the_user_id() {
+    let server = MockServer::start();
+    assert_eq!(client(&server, TOKEN).get_id().await.unwrap(), "usr!test");
+}
+
+#[tokio::test]
+async fn lists_mail_and_keeps_going_past_bad_records() {
+    let server = MockServer::start();
+    let mail = client(&server, TOKEN).get_mail(None).await.unwrap();
+
+    let ids: Vec<_> = mail.letters.iter().map(|l| l.id.as_deref()).collect();
+    assert_eq!(
+        ids,
+        [
+            Some("ltr!abc123"),
+            Some("pkg!xyz789"),
+            Some("rec123"),
+            Some("ltr!bare")
+        ]
+    );
+
+    assert_eq!(mail.errors.len(), 1);
+    assert_eq!(mail.errors[0].index, 3);
+    assert_eq!(mail.errors[0].id.as_deref(), Some("ltr!broken"));
+    assert!(mail.errors[0].to_string().contains("ltr!broken"));
+}
+
+#[tokio::test]
+async fn parses_every_timestamp_format_and_tolerates_bad_ones() {
+    let server = MockServer::start();
+    let letters = client(&server, TOKEN).get_mail(None).await.unwrap().letters;
+
+    assert_eq!(
+        letters[0].created_at,
+        Some(Utc.with_ymd_and_hms(2025, 6, 1, 16, 0, 0).unwrap())
+    );
+    assert_eq!(
+        letters[1].created_at,
+        Some(Utc.with_ymd_and_hms(2025, 5, 20, 10, 0, 0).unwrap())
+    );
+    assert_eq!(letters[2].created_at, None);
+    assert_eq!(letters[3].created_at, None);
+}
+
+#[tokio::test]
+async fn handles_null_tags_missing_fields_and_unknown_fields() {
+    let server = MockServer::start();
+    let letters = client(&server, TOKEN).get_mail(None).await.unwrap().letters;
+
+    assert_eq!(letters[1].tags, None);
+    assert_eq!(letters[1].extra["carrier"], "USPS");
+
+    let bare = &letters[3];
+    assert_eq!(bare.title, None);
+    assert_eq!(bare.status, None);
+    assert_eq!(
+        bare.tags,
+        Some(vec![String::from("orpheus"), String::from("3")])
+    );
+}
+
+#[tokio::test]
+async fn lists_each_type_from_its_own_endpoint() {
+    let server = MockServer::start();
+    let client = client(&server, TOKEN);
+
+    for (mail_type, id) in [
+        (MailType::Letter, "ltr!abc123"),
+        (MailType::Package, "pkg!xyz789"),
+        (MailType::Legacy, "rec123"),
+    ] {
+        let letters = client.get_mail(Some(mail_type)).await.unwrap().letters;
+        assert_eq!(letters.len(), 1);
+        assert_eq!(letters[0].id.as_deref(), Some(id));
+    }
+    assert_eq!(
+        server.requests(),
+        [
+            "/api/public/v1/letters",
+            "/api/public/v1/packages",
+            "/api/public/v1/lsv"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn looks_up_letters_and_packages_in_one_request() {
+    let server = MockServer::start();
+    let client = client(&server, TOKEN);
+
+    let letter = client.get_by_id("ltr!abc123", None).await.unwrap();
+    assert_eq!(letter.title.as_deref(), Some("Shipwrecked stickers"));
+    assert_eq!(letter.events.unwrap().len(), 2);
+
+    let package = client.get_by_id("pkg!xyz789", None).await.unwrap();
+    let events = package.events.unwrap();
+    assert_eq!(
+        events[0].happened_at,
+        Some(Utc.with_ymd_and_hms(2025, 5, 21, 0, 0, 0).unwrap())
+    );
+    assert_eq!(events[1].happened_at, None);
+
+    assert_eq!(
+        server.requests(),
+        [
+            "/api/public/v1/letters/ltr!abc123",
+            "/api/public/v1/packages/pkg!xyz789"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn looks_up_legacy_records() {
+    let server = MockServer::start();
+    let client = client(&server, TOKEN);
+
+    let record = client.get_by_id("msr/rec123", None).await.unwrap();
+    assert_eq!(record.id.as_deref(), Some("rec123"));
+    assert_eq!(server.requests(), ["/api/public/v1/lsv/msr/rec123"]);
+}
+
+#[tokio::test]
+async fn falls_back_to_searching_the_mail_list() {
+    let server = MockServer::start();
+    let client = client(&server, TOKEN);
+
+    let record = client.get_by_id("rec123", None).await.unwrap();
+    assert_eq!(record.title.as_deref(), Some("Arcade prize"));
+    assert_eq!(
+        server.requests(),
+        ["/api/public/v1/mail", "/api/public/v1/lsv/msr/rec123"]
+    );
+
+    assert!(matches!(
+        client.get_by_id("ltr!nothere", None).await,
+        Err(MailError::NotFound)
+    ));
+    assert!(matches!(
+        client.get_by_id("nothere", None).await,
+        Err(MailError::NotFound)
+    ));
+}
+
+#[tokio::test]
+async fn maps_failures_onto_error_variants() {
+    let server = MockServer::start();
+
+    let err = client(&server, EXPIRED_TOKEN).get_mail(None).await;
+    assert!(matches!(err, Err(MailError::Unauthorized)));
+
+    let err = client(&server, DOWN_TOKEN).get_mail(None).await;
+    assert!(matches!(err, Err(MailError::Server(status)) if status == 503));
+
+    let err = client(&server, GARBAGE_TOKEN).get_mail(None).await;
+    assert!(matches!(err, Err(MailError::Decode(_))));
+
+    let err = client(&server, EMPTY_TOKEN).get_mail(None).await;
+    assert!(matches!(err, Err(MailError::Schema(key)) if key == "mail"));
+
+    let err = client(&server, EMPTY_TOKEN).get_id().await;
+    assert!(matches!(err, Err(MailError::Schema(_))));
+}
+
+#[tokio::test]
+async fn reports_network_failures() {
+    let client = MailClient::builder(TOKEN)
+        .base_url("http://127.0.0.1:1")
+        .retry(Retry::none())
+        .build()
+        .unwrap();
+    let err = client.get_mail(None).await.unwrap_err();
+    assert!(matches!(err, MailError::Network(_)));
+    assert_eq!(err.exit_code(), 16);
+}
+
+#[test]
+fn rejects_instance_urls_that_arent_http() {
+    for base in [
+        "mail.hackclub.com",
+        "ftp://mail.hackclub.com",
+        "https://mail.hackclub.com/?a=b",
+    ] {
+        let err = MailClient::builder(TOKEN).base_url(base).build().err();
+        assert!(matches!(err, Some(MailError::InvalidUrl(_))), "{base}");
+    }
+}
+
+#[test]
+fn builds_urls_without_doubled_slashes() {
+    let instance = Instance::new("https://example.com/mail/", "api/v2").unwrap();
+    assert_eq!(instance.base(), "https://example.com/mail");
+    assert_eq!(instance.api_path(), "/api/v2/");
+    assert_eq!(
+        instance.api_url(&["letters", "ltr!abc123"]).as_str(),
+        "https://example.com/mail/api/v2/letters/ltr!abc123"
+    );
+    assert_eq!(
+        instance.api_url(&["letters", "../me?x"]).as_str(),
+        "https://example.com/mail/api/v2/letters/..%2Fme%3Fx"
+    );
+    assert_eq!(
+        instance.url("/api/v2/packages/pkg!xyz789").as_str(),
+        "https://example.com/mail/api/v2/packages/pkg!xyz789"
+    );
+}
+
+#[tokio::test]
+async fn uses_a_custom_api_path() {
+    let server = MockServer::start();
+    let client = MailClient::builder(TOKEN)
+        .base_url(format!("{}/", server.url))
+        .api_path("api//public/v1")
+        .build()
+        .unwrap();
+    assert_eq!(client.get_id().await.unwrap(), "usr!test");
+    assert_eq!(server.requests(), ["/api/public/v1/me"]);
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let server = MockServer::start();
+    let client = MailClient::builder(FLAKY_TOKEN)
+        .base_url(&server.url)
+        .retry(quick_retries())
+        .build()
+        .unwrap();
+
+    assert_eq!(client.get_id().await.unwrap(), "usr!test");
+    assert_eq!(server.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn gives_up_after_the_last_retry() {
+    let server = MockServer::start();
+    let client = MailClient::builder(DOWN_TOKEN)
+        .base_url(&server.url)
+        .retry(quick_retries())
+        .build()
+        .unwrap();
+
+    assert!(matches!(client.get_id().await, Err(MailError::Server(_))));
+    assert_eq!(server.requests().len(), 3);
+
+    // a rejected token isn't going to get better
+    let client = MailClient::builder(EXPIRED_TOKEN)
+        .base_url(&server.url)
+        .retry(quick_retries())
+        .build()
+        .unwrap();
+    assert!(matches!(
+        client.get_id().await,
+        Err(MailError::Unauthorized)
+    ));
+    assert_eq!(server.requests().len(), 4);
+}
+
+#[tokio::test]
+async fn waits_as_long_as_retry_after_says() {
+    let server = MockServer::start();
+    let client = MailClient::builder(LIMITED_TOKEN)
+        .base_url(&server.url)
+        .retry(quick_retries())
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    assert_eq!(client.get_id().await.unwrap(), "usr!test");
+    assert!(started.elapsed() >= Duration::from_secs(1));
+
+    // unless that's longer than it's willing to wait
+    let server = MockServer::start();
+    let client = MailClient::builder(LIMITED_TOKEN)
+        .base_url(&server.url)
+        .retry(Retry {
+            max_delay: Duration::from_millis(100),
+            ..quick_retries()
+        })
+        .build()
+        .unwrap();
+    assert!(matches!(client.get_id().await, Err(MailError::RateLimited)));
+}
+
+#[tokio::test]
+async fn times_out_slow_requests() {
+    let server = MockServer::start();
+    let client = MailClient::builder(SLOW_TOKEN)
+        .base_url(&server.url)
+        .timeout(Duration::from_millis(200))
+        .retry(Retry::none())
+        .build()
+        .unwrap();
+
+    let err = client.get_id().await.unwrap_err();
+    assert!(matches!(&err, MailError::Network(err) if err.is_timeout()));
+}
+
+#[tokio::test]
+async fn spaces_requests_out_when_rate_limited() {
+    let server = MockServer::start();
+    let client = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .rate_limit(10)
+        .build()
+        .unwrap();
+
+    let started = Instant::now();
+    for _ in 0..4 {
+        client.get_id().await.unwrap();
+    }
+    assert!(started.elapsed() >= Duration::from_millis(300));
+}
+
+#[test]
+fn doubles_the_delay_between_retries() {
+    let retry = Retry::default();
+    assert_eq!(retry.delay(1), Duration::from_millis(500));
+    assert_eq!(retry.delay(3), Duration::from_secs(2));
+    assert_eq!(retry.delay(20), retry.max_delay);
+}
+
+#[tokio::test]
+async fn falls_back_on_the_cache_when_the_server_is_down() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let cached = |token: &str| {
+        MailClient::builder(token)
+            .base_url(&server.url)
+            .retry(Retry::none())
+            .cache(Cache::new(dir.path()))
+            .build()
+            .unwrap()
+    };
+
+    let online = cached(TOKEN);
+    assert_eq!(online.get_mail(None).await.unwrap().letters.len(), 4);
+    assert_eq!(online.cached_at(), None);
+
+    let down = cached(DOWN_TOKEN);
+    assert_eq!(down.get_mail(None).await.unwrap().letters.len(), 4);
+    assert!(down.cached_at().is_some());
+    assert!(down.take_cached_at().is_some());
+    assert_eq!(down.cached_at(), None);
+
+    // only blips fall back, a rejected token still fails
+    let rejected = cached(EXPIRED_TOKEN);
+    assert!(matches!(
+        rejected.get_mail(None).await,
+        Err(MailError::Unauthorized)
+    ));
+}
+
+#[tokio::test]
+async fn outages_arent_hidden_without_stale_fallback() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let cached = |token: &str| {
+        MailClient::builder(token)
+            .base_url(&server.url)
+            .retry(Retry::none())
+            .cache(Cache::new(dir.path()))
+            .stale_fallback(false)
+            .build()
+            .unwrap()
+    };
+
+    assert_eq!(cached(TOKEN).get_mail(None).await.unwrap().letters.len(), 4);
+    let down = cached(DOWN_TOKEN);
+    assert!(matches!(
+        down.get_mail(None).await,
+        Err(MailError::Server(_))
+    ));
+    assert_eq!(down.cached_at(), None);
+}
+
+#[tokio::test]
+async fn offline_only_answers_from_the_cache() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let offline = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .cache(Cache::new(dir.path()))
+        .offline(true)
+        .build()
+        .unwrap();
+
+    assert!(matches!(offline.get_id().await, Err(MailError::NotCached)));
+
+    client(&server, TOKEN).get_id().await.unwrap();
+    let online = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .cache(Cache::new(dir.path()))
+        .build()
+        .unwrap();
+    online.get_letter("ltr!abc123").await.unwrap();
+    let requests = server.requests().len();
+
+    let letter = offline.get_letter("ltr!abc123").await.unwrap();
+    assert_eq!(letter.id.as_deref(), Some("ltr!abc123"));
+    assert!(offline.cached_at().is_some());
+    assert_eq!(server.requests().len(), requests);
+}
+
+#[tokio::test]
+async fn fresh_enough_responses_come_from_the_cache() {
+    let server = MockServer::start();
+    let dir = tempfile::tempdir().unwrap();
+    let client = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .cache(Cache::new(dir.path()))
+        .max_age(Duration::from_secs(600))
+        .build()
+        .unwrap();
+
+    client.get_mail(Some(MailType::Package)).await.unwrap();
+    client.get_mail(Some(MailType::Package)).await.unwrap();
+    client.get_mail(Some(MailType::Letter)).await.unwrap();
+    assert_eq!(
+        server.requests(),
+        ["/api/public/v1/packages", "/api/public/v1/letters"]
+    );
+}
+
+#[tokio::test]
+async fn gets_many_at_once_in_order() {
+    let server = MockServer::start();
+    let client = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .retry(Retry::none())
+        .concurrency(2)
+        .build()
+        .unwrap();
+    let ids = ["pkg!xyz789", "ltr!nothere", "ltr!abc123", "msr/rec123"].map(String::from);
+
+    let results = client.get_many(&ids, None).await;
+    assert_eq!(results.len(), 4);
+    assert_eq!(
+        results[0].as_ref().unwrap().id.as_deref(),
+        Some("pkg!xyz789")
+    );
+    assert!(matches!(results[1], Err(MailError::NotFound)));
+    assert_eq!(
+        results[2].as_ref().unwrap().id.as_deref(),
+        Some("ltr!abc123")
+    );
+    assert_eq!(results[3].as_ref().unwrap().id.as_deref(), Some("rec123"));
+}
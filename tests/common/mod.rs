@@ -0,0 +1,288 @@
+// Not every test binary uses every helper
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+#[cfg(feature = "cli")]
+use std::process::Command;
+use std::process::Output;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use orphy::Letter;
+#[cfg(feature = "cli")]
+use tempfile::TempDir;
+
+/// Token the mock accepts.
+pub const TOKEN: &str = "good-token";
+/// Token the mock rejects with a 401.
+pub const EXPIRED_TOKEN: &str = "expired-token";
+/// Token that makes the mock answer every request with a 503.
+pub const DOWN_TOKEN: &str = "down-token";
+/// Token that makes the mock answer with a body that isn't json.
+pub const GARBAGE_TOKEN: &str = "garbage-token";
+/// Token that makes the mock answer with json that's missing every expected key.
+pub const EMPTY_TOKEN: &str = "empty-token";
+/// Token that makes the mock answer the first request for each path with a 503, and like
+/// [`TOKEN`] after that.
+pub const FLAKY_TOKEN: &str = "flaky-token";
+/// Token that makes the mock rate limit the first request for each path for a second.
+pub const LIMITED_TOKEN: &str = "limited-token";
+/// Token that makes the mock take a few seconds to answer.
+pub const SLOW_TOKEN: &str = "slow-token";
+
+/// A stand-in for Hack Club Mail serving the json in `tests/fixtures`.
+pub struct MockServer {
+    pub url: String,
+    requests: Arc<Mutex<Vec<String>>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let log = requests.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let log = log.clone();
+                thread::spawn(move || handle(stream, &log));
+            }
+        });
+
+        Self { url, requests }
+    }
+
+    /// Paths requested so far, exactly as they were sent.
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+fn handle(mut stream: TcpStream, log: &Mutex<Vec<String>>) {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    if reader.read_line(&mut request_line).is_err() {
+        return;
+    }
+    let mut token = String::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("authorization")
+        {
+            token = value.trim().trim_start_matches("Bearer ").to_string();
+        }
+    }
+
+    let path = request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string();
+    let first_try = {
+        let mut log = log.lock().unwrap();
+        log.push(path.clone());
+        log.iter().filter(|requested| **requested == path).count() == 1
+    };
+
+    let mut extra_headers = "";
+    let (status, body) = match token.as_str() {
+        FLAKY_TOKEN if first_try => (503, String::from(r#"{"error":"blip"}"#)),
+        LIMITED_TOKEN if first_try => {
+            extra_headers = "Retry-After: 1\r\n";
+            (429, String::from(r#"{"error":"slow down"}"#))
+        }
+        FLAKY_TOKEN | LIMITED_TOKEN => respond(TOKEN, &path),
+        SLOW_TOKEN => {
+            thread::sleep(Duration::from_secs(3));
+            respond(TOKEN, &path)
+        }
+        token => respond(token, &path),
+    };
+    let reason = match status {
+        200 => "OK",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        _ => "Service Unavailable",
+    };
+    let response = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{extra_headers}Connection: close\r\n\r\n{body}",
+        body.len()
+    );
+    let _ = stream.write_all(response.as_bytes());
+}
+
+fn respond(token: &str, path: &str) -> (u16, String) {
+    match token {
+        TOKEN => {}
+        DOWN_TOKEN => return (503, String::from(r#"{"error":"down for maintenance"}"#)),
+        GARBAGE_TOKEN => return (200, String::from("<html>definitely not json</html>")),
+        EMPTY_TOKEN => return (200, String::from("{}")),
+        _ => return (401, String::from(r#"{"error":"invalid token"}"#)),
+    }
+
+    let fixture = match path.strip_prefix("/api/public/v1") {
+        Some("/me") => "me",
+        Some("/mail") => "mail",
+        Some("/letters") => "letters",
+        Some("/packages") => "packages",
+        Some("/lsv") => "lsv",
+        Some("/letters/ltr!abc123") => "letter",
+        Some("/packages/pkg!xyz789") => "package",
+        Some("/lsv/msr/rec123") => "lsv_record",
+        _ => return (404, String::from(r#"{"error":"not found"}"#)),
+    };
+    (200, fixture_json(fixture))
+}
+
+pub fn fixture_json(name: &str) -> String {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(format!("{name}.json"));
+    std::fs::read_to_string(path).unwrap()
+}
+
+/// The mail in `tests/fixtures/timeline.json`: two packages and a letter with a few months of
+/// tracking events between them, and a letter with nothing filled in.
+pub fn timeline() -> Vec<Letter> {
+    serde_json::from_str(&fixture_json("timeline")).unwrap()
+}
+
+/// A request a [`WebhookReceiver`] got.
+#[derive(Clone, Debug)]
+pub struct Delivery {
+    /// Header names are lowercase.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl Delivery {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// A local http endpoint for webhooks to POST to. It answers with `statuses` in order, and with
+/// the last one after that.
+pub struct WebhookReceiver {
+    pub url: String,
+    deliveries: Arc<Mutex<Vec<Delivery>>>,
+}
+
+impl WebhookReceiver {
+    pub fn start(statuses: &[u16]) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/hooks/orphy", listener.local_addr().unwrap());
+        let deliveries = Arc::new(Mutex::new(Vec::new()));
+        let statuses = statuses.to_vec();
+
+        let log = deliveries.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let status = {
+                    let log = log.lock().unwrap();
+                    statuses[log.len().min(statuses.len() - 1)]
+                };
+                receive(stream, status, &log);
+            }
+        });
+
+        Self { url, deliveries }
+    }
+
+    pub fn deliveries(&self) -> Vec<Delivery> {
+        self.deliveries.lock().unwrap().clone()
+    }
+}
+
+fn receive(mut stream: TcpStream, status: u16, log: &Mutex<Vec<Delivery>>) -> Option<()> {
+    let mut reader = BufReader::new(stream.try_clone().unwrap());
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).ok()?;
+    let mut headers = Vec::new();
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).unwrap_or(0) == 0 || header == "\r\n" {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    let length = headers
+        .iter()
+        .find(|(name, _)| name == "content-length")
+        .and_then(|(_, value)| value.parse().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).ok()?;
+    // logged before answering, so it's there as soon as the sender knows it was delivered
+    log.lock().unwrap().push(Delivery {
+        headers,
+        body: String::from_utf8(body).unwrap(),
+    });
+
+    let response =
+        format!("HTTP/1.1 {status} Whatever\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+    let _ = stream.write_all(response.as_bytes());
+    Some(())
+}
+
+/// Runs the orphy binary with its config kept in a temporary directory.
+#[cfg(feature = "cli")]
+pub struct Orphy {
+    pub config_dir: TempDir,
+    instance: String,
+}
+
+#[cfg(feature = "cli")]
+impl Orphy {
+    pub fn new(server: &MockServer) -> Self {
+        Self {
+            config_dir: tempfile::tempdir().unwrap(),
+            instance: server.url.clone(),
+        }
+    }
+
+    /// Like [`Orphy::new`], with `token` already saved through `orphy setup`. It isn't checked
+    /// with the server, so tokens it rejects can be saved too.
+    pub fn with_token(server: &MockServer, token: &str) -> Self {
+        let orphy = Self::new(server);
+        let output = orphy.run(&["setup", token, "--no-verify"]);
+        assert!(output.status.success(), "{}", stderr(&output));
+        orphy
+    }
+
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_orphy"));
+        command
+            .args(args)
+            .env("HOME", self.config_dir.path())
+            .env("XDG_CONFIG_HOME", self.config_dir.path())
+            .env("ORPHY_INSTANCE", &self.instance);
+        command
+    }
+
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().unwrap()
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
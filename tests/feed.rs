@@ -0,0 +1,90 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::timeline;
+use orphy::feed;
+use serde_json::json;
+
+fn now() -> chrono::DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap()
+}
+
+#[test]
+fn entries_are_new_mail_and_events_newest_first() {
+    let mail = timeline();
+    let entries = feed::entries(&mail);
+    let guids: Vec<&str> = entries.iter().map(|entry| entry.guid.as_str()).collect();
+    assert_eq!(
+        guids,
+        [
+            "urn:orphy:ltr!3:20250608T000000Z:1",
+            "urn:orphy:ltr!3",
+            "urn:orphy:pkg!2:20250511T000000Z:1",
+            "urn:orphy:pkg!2:20250510T120000Z:1",
+            "urn:orphy:pkg!2:20250510T120000Z:2",
+            "urn:orphy:pkg!2",
+            "urn:orphy:pkg!1:20250504T000000Z:1",
+            "urn:orphy:pkg!1:20250502T000000Z:1",
+            "urn:orphy:pkg!1",
+        ]
+    );
+    assert_eq!(entries[1].title, "New letter: Shipwrecked stickers");
+    assert_eq!(entries[3].title, "Hoodie & stickers: Accepted");
+    assert_eq!(
+        entries[3].link,
+        Some("https://tools.usps.com/go/TrackConfirmAction?tLabels=9400100000000000000006")
+    );
+}
+
+#[test]
+fn guids_stay_the_same_when_more_events_come_in() {
+    let before: Vec<String> = feed::entries(&timeline())
+        .into_iter()
+        .map(|entry| entry.guid)
+        .collect();
+
+    let mut mail = timeline();
+    mail[1].events.as_mut().unwrap().push(
+        serde_json::from_value(
+            json!({ "happened_at": "2025-05-10T12:00:00Z", "description": "Processed" }),
+        )
+        .unwrap(),
+    );
+    let after: Vec<String> = feed::entries(&mail)
+        .into_iter()
+        .map(|entry| entry.guid)
+        .collect();
+
+    assert_eq!(after.len(), before.len() + 1);
+    assert!(before.iter().all(|guid| after.contains(guid)));
+}
+
+#[test]
+fn atom_feed() {
+    let atom = feed::atom(&timeline(), "https://mail.hackclub.com", now());
+    assert!(atom.contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">"));
+    // updated is the newest entry, not when the feed was made
+    assert!(atom.contains("  <updated>2025-06-08T00:00:00+00:00</updated>"));
+    assert!(atom.contains("<id>urn:orphy:pkg!2:20250510T120000Z:1</id>"));
+    assert!(atom.contains("<title>Hoodie &amp; stickers: Accepted</title>"));
+    assert!(atom.contains("<link href=\"https://mail.hackclub.com/letters/ltr!3\"/>"));
+    assert!(atom.contains("<summary>Status: shipped\nBurlington VT</summary>"));
+    assert_eq!(atom.matches("<entry>").count(), 9);
+}
+
+#[test]
+fn rss_feed() {
+    let rss = feed::rss(&timeline(), "https://mail.hackclub.com", now());
+    assert!(rss.contains("<rss version=\"2.0\">"));
+    assert!(rss.contains("<link>https://mail.hackclub.com</link>"));
+    assert!(rss.contains("<guid isPermaLink=\"false\">urn:orphy:ltr!3</guid>"));
+    assert!(rss.contains("<pubDate>Sat, 10 May 2025 12:00:00 +0000</pubDate>"));
+    assert_eq!(rss.matches("<item>").count(), 9);
+}
+
+#[test]
+fn empty_feeds_were_updated_now() {
+    let atom = feed::atom(&[], "https://mail.hackclub.com", now());
+    assert!(atom.contains("<updated>2025-07-01T00:00:00+00:00</updated>"));
+    assert!(!atom.contains("<entry>"));
+}
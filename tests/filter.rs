@@ -0,0 +1,89 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::timeline;
+use orphy::filter::{DateField, SortKey, sort};
+use orphy::{Letter, MailFilter};
+use serde_json::json;
+
+fn mail() -> Vec<Letter> {
+    let mut mail = timeline();
+    // no title, so only its tags can be searched
+    mail.push(
+        serde_json::from_value(json!({
+            "id": "pkg!5", "status": "shipped", "tags": ["arcade", "hoodie"],
+            "created_at": "2025-07-04T00:00:00Z", "updated_at": "2025-07-05T00:00:00Z"
+        }))
+        .unwrap(),
+    );
+    mail
+}
+
+fn ids(letters: &[Letter]) -> Vec<&str> {
+    letters
+        .iter()
+        .map(|letter| letter.id.as_deref().unwrap())
+        .collect()
+}
+
+#[test]
+fn empty_filter_matches_everything() {
+    assert_eq!(MailFilter::default().apply(mail()).len(), 5);
+}
+
+#[test]
+fn filters_by_status_and_tags_ignoring_case() {
+    let filter = MailFilter {
+        status: Some(String::from("DELIVERED")),
+        ..Default::default()
+    };
+    assert_eq!(ids(&filter.apply(mail())), ["pkg!1", "ltr!3"]);
+
+    let filter = MailFilter {
+        tags: vec![String::from("stickers"), String::from("SHIPWRECKED")],
+        ..Default::default()
+    };
+    assert_eq!(ids(&filter.apply(mail())), ["ltr!3"]);
+}
+
+#[test]
+fn filters_by_date_range() {
+    let filter = MailFilter {
+        since: Some(Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()),
+        until: Some(Utc.with_ymd_and_hms(2025, 7, 4, 0, 0, 0).unwrap()),
+        ..Default::default()
+    };
+    assert_eq!(ids(&filter.apply(mail())), ["ltr!3", "pkg!5"]);
+
+    let filter = MailFilter {
+        since: Some(Utc.with_ymd_and_hms(2025, 6, 5, 0, 0, 0).unwrap()),
+        date_field: DateField::Updated,
+        ..Default::default()
+    };
+    assert_eq!(ids(&filter.apply(mail())), ["ltr!3", "pkg!5"]);
+}
+
+#[test]
+fn searches_titles_and_tags() {
+    let filter = MailFilter {
+        search: Some(String::from("HOOD")),
+        ..Default::default()
+    };
+    assert_eq!(ids(&filter.apply(mail())), ["pkg!1", "pkg!2", "pkg!5"]);
+}
+
+#[test]
+fn sorts_with_missing_values_last() {
+    let mut letters = mail();
+    sort(&mut letters, SortKey::Created);
+    assert_eq!(ids(&letters), ["pkg!1", "pkg!2", "ltr!3", "pkg!5", "ltr!4"]);
+
+    sort(&mut letters, SortKey::Title);
+    assert_eq!(ids(&letters), ["pkg!2", "pkg!1", "ltr!3", "pkg!5", "ltr!4"]);
+
+    sort(&mut letters, SortKey::Status);
+    assert_eq!(ids(&letters), ["pkg!1", "ltr!3", "pkg!2", "pkg!5", "ltr!4"]);
+
+    sort(&mut letters, SortKey::Updated);
+    assert_eq!(ids(&letters), ["pkg!1", "pkg!2", "ltr!3", "pkg!5", "ltr!4"]);
+}
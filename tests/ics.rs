@@ -0,0 +1,61 @@
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::timeline;
+use orphy::ics;
+
+fn calendar() -> String {
+    ics::calendar(
+        &timeline(),
+        Utc.with_ymd_and_hms(2025, 7, 1, 0, 0, 0).unwrap(),
+    )
+}
+
+// the calendar with folded lines joined back up
+fn unfolded() -> String {
+    calendar().replace("\r\n ", "")
+}
+
+#[test]
+fn is_a_calendar_with_crlf_lines_of_at_most_75_bytes() {
+    let calendar = calendar();
+    assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+    assert!(calendar.ends_with("END:VCALENDAR\r\n"));
+    assert!(!calendar.replace("\r\n", "").contains('\n'));
+    assert!(calendar.split("\r\n").all(|line| line.len() <= 75));
+    assert!(unfolded().contains("DTSTAMP:20250701T000000Z"));
+}
+
+#[test]
+fn has_an_event_for_every_milestone() {
+    let calendar = unfolded();
+    assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 10);
+    assert!(calendar.contains(
+        "UID:pkg!1-created@orphy\r\nDTSTAMP:20250701T000000Z\r\nDTSTART:20250501T000000Z"
+    ));
+    assert!(calendar.contains("SUMMARY:Hoodie\\, size M: Accepted"));
+    // events without a time can't go on a calendar
+    assert!(!calendar.contains("In transit"));
+    // mail marked delivered without a delivery scan is delivered at its last event
+    assert!(calendar.contains(
+        "UID:ltr!3-delivered@orphy\r\nDTSTAMP:20250701T000000Z\r\nDTSTART:20250608T000000Z\r\nSUMMARY:Shipwrecked stickers delivered"
+    ));
+    // and mail with a delivery scan isn't delivered twice
+    assert!(!calendar.contains("UID:pkg!1-delivered@orphy"));
+}
+
+#[test]
+fn events_at_the_same_time_get_their_own_uid() {
+    let calendar = unfolded();
+    assert!(calendar.contains("UID:pkg!2-20250510T120000Z-1@orphy"));
+    assert!(calendar.contains("UID:pkg!2-20250510T120000Z-2@orphy"));
+}
+
+#[test]
+fn descriptions_have_the_status_place_and_links() {
+    let calendar = unfolded();
+    assert!(calendar.contains(
+        "DESCRIPTION:Status: delivered\\nBurlington VT\\nhttps://tools.usps.com/go/TrackConfirmAction?tLabels=9400111899223456789012"
+    ));
+    assert!(calendar.contains("URL:https://mail.hackclub.com/letters/ltr!3"));
+}
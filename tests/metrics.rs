@@ -0,0 +1,154 @@
+mod common;
+
+use chrono::{DateTime, Utc};
+use common::timeline;
+use orphy::metrics::{prometheus, refreshes};
+use orphy::{Letter, MailError, RequestMetrics};
+use serde_json::json;
+use std::time::Duration;
+
+fn mail() -> Vec<Letter> {
+    let mut mail = timeline();
+    mail.extend(
+        serde_json::from_value::<Vec<Letter>>(json!([
+            { "id": "ltr!5", "type": "letter", "status": "mailed", "created_at": "2025-06-05T00:00:00Z" },
+            { "id": "ltr!6", "type": "letter", "status": "mailed" },
+            { "id": "rec7", "status": "say \"hi\"" }
+        ]))
+        .unwrap(),
+    );
+    mail
+}
+
+fn now() -> DateTime<Utc> {
+    "2025-06-10T00:00:00Z".parse().unwrap()
+}
+
+fn sample<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    text.lines()
+        .find_map(|line| line.strip_prefix(name)?.strip_prefix(' '))
+}
+
+#[test]
+fn counts_mail_by_type_and_status() {
+    let text = prometheus(&mail(), &RequestMetrics::default(), now());
+
+    assert!(text.contains("# TYPE orphy_mail gauge\n"));
+    assert_eq!(
+        sample(&text, r#"orphy_mail{type="letter",status="mailed"}"#),
+        Some("2")
+    );
+    assert_eq!(
+        sample(&text, r#"orphy_mail{type="package",status="delivered"}"#),
+        Some("1")
+    );
+    assert_eq!(
+        sample(&text, r#"orphy_mail{type="unknown",status="say \"hi\""}"#),
+        Some("1")
+    );
+}
+
+#[test]
+fn ages_and_timestamps_of_the_mail() {
+    let text = prometheus(&mail(), &RequestMetrics::default(), now());
+
+    // pkg!1 is delivered, so pkg!2 is the oldest that isn't
+    assert_eq!(
+        sample(&text, "orphy_oldest_undelivered_age_seconds"),
+        Some("2678400")
+    );
+    assert_eq!(
+        sample(&text, "orphy_last_event_timestamp_seconds"),
+        Some("1749340800")
+    );
+
+    let text = prometheus(&[], &RequestMetrics::default(), now());
+    assert_eq!(
+        sample(&text, "orphy_oldest_undelivered_age_seconds"),
+        Some("0")
+    );
+    assert_eq!(sample(&text, "orphy_last_event_timestamp_seconds"), None);
+}
+
+#[test]
+fn requests_are_counted_in_a_histogram() {
+    let mut requests = RequestMetrics::default();
+    requests.record(Duration::from_millis(80), None);
+    requests.record(Duration::from_secs(3), Some(&MailError::RateLimited));
+    requests.record(Duration::from_secs(60), Some(&MailError::RateLimited));
+    let text = prometheus(&[], &requests, now());
+
+    assert!(text.contains("# TYPE orphy_api_request_duration_seconds histogram\n"));
+    assert_eq!(sample(&text, "orphy_api_requests_total"), Some("3"));
+    assert_eq!(
+        sample(
+            &text,
+            r#"orphy_api_request_errors_total{kind="rate_limited"}"#
+        ),
+        Some("2")
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"orphy_api_request_duration_seconds_bucket{le="0.05"}"#
+        ),
+        Some("0")
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"orphy_api_request_duration_seconds_bucket{le="0.1"}"#
+        ),
+        Some("1")
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"orphy_api_request_duration_seconds_bucket{le="5"}"#
+        ),
+        Some("2")
+    );
+    assert_eq!(
+        sample(
+            &text,
+            r#"orphy_api_request_duration_seconds_bucket{le="+Inf"}"#
+        ),
+        Some("3")
+    );
+    assert_eq!(
+        sample(&text, "orphy_api_request_duration_seconds_sum"),
+        Some("63.08")
+    );
+    assert_eq!(
+        sample(&text, "orphy_api_request_duration_seconds_count"),
+        Some("3")
+    );
+}
+
+#[test]
+fn every_metric_has_help_and_a_type() {
+    let text = prometheus(&mail(), &RequestMetrics::default(), now());
+    for line in text.lines().filter(|line| !line.starts_with('#')) {
+        let name = line.split(['{', ' ']).next().unwrap();
+        let family = name
+            .strip_suffix("_bucket")
+            .or_else(|| name.strip_suffix("_sum"))
+            .or_else(|| name.strip_suffix("_count"))
+            .unwrap_or(name);
+        assert!(text.contains(&format!("# HELP {family} ")), "{line}");
+        assert!(text.contains(&format!("# TYPE {family} ")), "{line}");
+    }
+}
+
+#[test]
+fn refreshes_say_how_old_the_mail_is() {
+    let text = refreshes(now(), 3);
+
+    assert!(text.contains("# TYPE orphy_last_refresh_timestamp_seconds gauge\n"));
+    assert_eq!(
+        sample(&text, "orphy_last_refresh_timestamp_seconds"),
+        Some("1749513600")
+    );
+    assert!(text.contains("# TYPE orphy_refresh_errors_total counter\n"));
+    assert_eq!(sample(&text, "orphy_refresh_errors_total"), Some("3"));
+}
@@ -0,0 +1,126 @@
+use orphy::notify::summary;
+use orphy::{Change, Letter, Notifier, NotifyError};
+use serde_json::{Value, json};
+
+fn changes() -> Vec<Change> {
+    let letter: Letter = serde_json::from_value(json!({
+        "id": "pkg!xyz789", "title": "Hoodie", "type": "package", "status": "shipped"
+    }))
+    .unwrap();
+    vec![
+        Change::StatusChanged {
+            letter: letter.clone(),
+            from: Some(String::from("pending")),
+            to: Some(String::from("shipped")),
+        },
+        Change::NewEvent {
+            letter,
+            event: serde_json::from_value(
+                json!({ "description": "Accepted", "facility": "Burlington VT" }),
+            )
+            .unwrap(),
+        },
+    ]
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn commands_get_the_change_in_environment_variables() {
+    let dir = tempfile::tempdir().unwrap();
+    let out = dir.path().join("out");
+    let notifier = Notifier {
+        command: Some(format!(
+            "printf '%s|%s|%s|%s|%s\\n' \"$ORPHY_KIND\" \"$ORPHY_ID\" \"$ORPHY_TITLE\" \"$ORPHY_STATUS\" \"$ORPHY_DESCRIPTION\" >> '{}'",
+            out.display()
+        )),
+        ..Default::default()
+    };
+
+    notifier.notify(&changes()).await.unwrap();
+    assert_eq!(
+        std::fs::read_to_string(out).unwrap(),
+        "status_changed|pkg!xyz789|Hoodie|shipped|pending -> shipped\n\
+         new_event|pkg!xyz789|Hoodie|shipped|Accepted at Burlington VT\n"
+    );
+}
+
+#[tokio::test]
+async fn files_get_a_line_of_json_per_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("changes.ndjson");
+    let notifier = Notifier {
+        file: Some(path.clone()),
+        ..Default::default()
+    };
+
+    notifier.notify(&changes()).await.unwrap();
+    notifier.notify(&changes()[..1]).await.unwrap();
+    let lines: Vec<Value> = std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[1]["kind"], "new_event");
+    assert_eq!(lines[1]["event"]["description"], "Accepted");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn a_failing_action_doesnt_stop_the_others() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("changes.ndjson");
+    let notifier = Notifier {
+        command: Some(String::from("exit 3")),
+        file: Some(path.clone()),
+        bell: false,
+    };
+
+    let err = notifier.notify(&changes()).await.unwrap_err();
+    assert!(matches!(err, NotifyError::Command { .. }));
+    assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 2);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pipes_nobody_reads_are_an_error() {
+    let dir = tempfile::tempdir().unwrap();
+    let pipe = dir.path().join("pipe");
+    let made = std::process::Command::new("mkfifo")
+        .arg(&pipe)
+        .status()
+        .unwrap();
+    assert!(made.success());
+    let notifier = Notifier {
+        file: Some(pipe),
+        ..Default::default()
+    };
+
+    let err = notifier.notify(&changes()).await.unwrap_err();
+    assert!(matches!(err, NotifyError::NoReader { .. }));
+}
+
+#[tokio::test]
+async fn nothing_happens_without_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("changes.ndjson");
+    let notifier = Notifier {
+        command: Some(String::from("exit 3")),
+        file: Some(path.clone()),
+        bell: true,
+    };
+
+    notifier.notify(&[]).await.unwrap();
+    assert!(!path.exists());
+    assert!(Notifier::default().is_empty());
+}
+
+#[test]
+fn summaries_for_the_bell() {
+    let changes = changes();
+    assert_eq!(
+        summary(&changes[1..]),
+        "pkg!xyz789 Hoodie: Accepted at Burlington VT"
+    );
+    assert_eq!(summary(&changes), "2 changes to your mail");
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use common::timeline;
+use orphy::Stats;
+use orphy::stats::FacilityCount;
+use std::time::Duration;
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+#[test]
+fn times_from_creation_to_the_first_scan_and_delivery() {
+    let stats = Stats::new(&timeline());
+    assert_eq!(stats.mail, 4);
+
+    let first = stats.to_first_event.unwrap();
+    assert_eq!(first.mail, 3);
+    assert_eq!(first.fastest, Duration::from_secs(12 * HOUR));
+    assert_eq!(first.median, Duration::from_secs(DAY));
+    assert_eq!(first.slowest, Duration::from_secs(7 * DAY));
+
+    // a delivery scan counts, and so does the last event of mail marked delivered
+    let delivery = stats.to_delivery.unwrap();
+    assert_eq!(delivery.mail, 2);
+    assert_eq!(delivery.average, Duration::from_secs(5 * DAY));
+}
+
+#[test]
+fn transit_per_type_and_subtype() {
+    let stats = Stats::new(&timeline());
+    let kinds: Vec<_> = stats
+        .kinds
+        .iter()
+        .map(|kind| {
+            (
+                kind.letter_type.as_deref(),
+                kind.letter_subtype.as_deref(),
+                kind.mail,
+                kind.to_delivery.map(|transit| transit.average),
+            )
+        })
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            (None, None, 1, None),
+            (
+                Some("letter"),
+                Some("flat"),
+                1,
+                Some(Duration::from_secs(7 * DAY))
+            ),
+            (Some("package"), None, 2, Some(Duration::from_secs(3 * DAY))),
+        ]
+    );
+}
+
+#[test]
+fn counts_statuses_facilities_and_months() {
+    let stats = Stats::new(&timeline());
+    assert_eq!(stats.statuses["delivered"], 2);
+    assert_eq!(stats.statuses["shipped"], 1);
+    assert_eq!(stats.statuses["no status"], 1);
+    assert_eq!(
+        stats.facilities,
+        [
+            FacilityCount {
+                facility: String::from("Burlington VT"),
+                events: 3
+            },
+            FacilityCount {
+                facility: String::from("Shelburne VT"),
+                events: 1
+            },
+        ]
+    );
+    assert_eq!(
+        stats.months.iter().collect::<Vec<_>>(),
+        [
+            (&String::from("2025-05"), &2),
+            (&String::from("2025-06"), &1)
+        ]
+    );
+}
+
+#[test]
+fn nothing_to_time_without_events() {
+    let stats = Stats::new(&[]);
+    assert_eq!(stats.mail, 0);
+    assert!(stats.to_first_event.is_none());
+    assert!(stats.to_delivery.is_none());
+    assert!(stats.kinds.is_empty());
+}
@@ -0,0 +1,132 @@
+use orphy::{Change, Letter, Store};
+use serde_json::json;
+
+fn letter(value: serde_json::Value) -> Letter {
+    serde_json::from_value(value).unwrap()
+}
+
+fn hoodie(status: &str, events: serde_json::Value) -> Letter {
+    letter(json!({
+        "id": "pkg!xyz789",
+        "title": "Hoodie",
+        "type": "package",
+        "status": status,
+        "created_at": "2025-05-20T10:00:00Z",
+        "events": events,
+    }))
+}
+
+#[test]
+fn first_sync_reports_everything_as_new() {
+    let mut store = Store::open_in_memory().unwrap();
+    assert_eq!(store.last_sync().unwrap(), None);
+
+    let changes = store
+        .sync(&[
+            hoodie("shipped", json!([{ "description": "Label created" }])),
+            letter(json!({ "id": "ltr!abc123", "status": "mailed" })),
+            letter(json!({ "title": "no id, can't be tracked" })),
+        ])
+        .unwrap();
+
+    assert_eq!(changes.len(), 2);
+    assert!(
+        changes
+            .iter()
+            .all(|change| matches!(change, Change::NewLetter { .. }))
+    );
+    assert!(store.last_sync().unwrap().is_some());
+}
+
+#[test]
+fn nothing_changes_when_nothing_changed() {
+    let mut store = Store::open_in_memory().unwrap();
+    let mail = [hoodie(
+        "shipped",
+        json!([{ "description": "Label created" }]),
+    )];
+
+    store.sync(&mail).unwrap();
+    assert!(store.sync(&mail).unwrap().is_empty());
+}
+
+#[test]
+fn reports_status_changes_and_new_events() {
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .sync(&[hoodie(
+            "shipped",
+            json!([{ "description": "Label created", "happened_at": "2025-05-21T09:00:00Z" }]),
+        )])
+        .unwrap();
+
+    let changes = store
+        .sync(&[hoodie(
+            "delivered",
+            json!([
+                { "description": "Delivered", "happened_at": "2025-05-24T15:00:00Z", "location": "Shelburne, VT" },
+                { "description": "Label created", "happened_at": "2025-05-21T09:00:00Z" },
+            ]),
+        )])
+        .unwrap();
+
+    assert_eq!(changes.len(), 2);
+    assert!(matches!(
+        &changes[0],
+        Change::StatusChanged { from, to, .. }
+            if from.as_deref() == Some("shipped") && to.as_deref() == Some("delivered")
+    ));
+    assert!(matches!(
+        &changes[1],
+        Change::NewEvent { event, .. } if event.description.as_deref() == Some("Delivered")
+    ));
+}
+
+#[test]
+fn syncing_without_events_keeps_the_known_ones() {
+    let mut store = Store::open_in_memory().unwrap();
+    store
+        .sync(&[hoodie(
+            "shipped",
+            json!([{ "description": "Label created" }]),
+        )])
+        .unwrap();
+
+    let changes = store
+        .sync(&[hoodie("shipped", serde_json::Value::Null)])
+        .unwrap();
+    assert!(changes.is_empty());
+
+    let stored = store.letter("pkg!xyz789").unwrap().unwrap();
+    assert_eq!(stored.events.unwrap().len(), 1);
+
+    // and the events found next time are still compared against the old ones
+    let changes = store
+        .sync(&[hoodie(
+            "shipped",
+            json!([{ "description": "Label created" }]),
+        )])
+        .unwrap();
+    assert!(changes.is_empty());
+}
+
+#[test]
+fn keeps_letters_between_opens() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history/history.sqlite3");
+
+    Store::open(&path)
+        .unwrap()
+        .sync(&[
+            hoodie("shipped", json!([])),
+            letter(json!({ "id": "ltr!abc123", "tags": ["stickers"] })),
+        ])
+        .unwrap();
+
+    let store = Store::open(&path).unwrap();
+    let letters = store.letters().unwrap();
+    assert_eq!(letters.len(), 2);
+    let stored = store.letter("ltr!abc123").unwrap().unwrap();
+    assert_eq!(stored.tags, Some(vec![String::from("stickers")]));
+    assert!(stored.events.is_none());
+}
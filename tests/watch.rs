@@ -0,0 +1,71 @@
+mod common;
+
+use common::{MockServer, TOKEN};
+use orphy::watch::backoff;
+use orphy::{Change, Letter, MailClient, Watcher};
+use serde_json::json;
+use std::time::Duration;
+
+fn letter(status: &str, events: usize) -> Letter {
+    let events: Vec<_> = (0..events)
+        .map(|i| json!({ "description": format!("scan {i}") }))
+        .collect();
+    serde_json::from_value(json!({ "id": "pkg!xyz789", "status": status, "events": events }))
+        .unwrap()
+}
+
+#[test]
+fn first_update_only_primes() {
+    let mut watcher = Watcher::all();
+    assert!(watcher.update(vec![letter("shipped", 1)]).is_empty());
+    assert_eq!(watcher.len(), 1);
+}
+
+#[test]
+fn reports_changes_after_priming() {
+    let mut watcher = Watcher::all();
+    watcher.update(vec![letter("shipped", 1)]);
+
+    assert!(watcher.update(vec![letter("shipped", 1)]).is_empty());
+
+    let changes = watcher.update(vec![letter("delivered", 2)]);
+    assert!(matches!(changes[0], Change::StatusChanged { .. }));
+    assert!(
+        matches!(&changes[1], Change::NewEvent { event, .. } if event.description.as_deref() == Some("scan 1"))
+    );
+
+    let mut new: Letter = serde_json::from_value(json!({ "id": "ltr!new" })).unwrap();
+    new.status = Some(String::from("pending"));
+    let changes = watcher.update(vec![letter("delivered", 2), new]);
+    assert!(matches!(&changes[..], [Change::NewLetter { .. }]));
+}
+
+#[tokio::test]
+async fn polls_a_single_piece_of_mail() {
+    let server = MockServer::start();
+    let client = MailClient::builder(TOKEN)
+        .base_url(&server.url)
+        .build()
+        .unwrap();
+    let mut watcher = Watcher::one("ltr!abc123", None);
+
+    assert!(watcher.poll(&client).await.unwrap().is_empty());
+    assert!(watcher.poll(&client).await.unwrap().is_empty());
+    assert_eq!(
+        server.requests(),
+        [
+            "/api/public/v1/letters/ltr!abc123",
+            "/api/public/v1/letters/ltr!abc123"
+        ]
+    );
+}
+
+#[test]
+fn backoff_doubles_up_to_an_hour() {
+    let minute = Duration::from_secs(60);
+    assert_eq!(backoff(minute, 0), minute);
+    assert_eq!(backoff(minute, 1), minute * 2);
+    assert_eq!(backoff(minute, 3), minute * 8);
+    assert_eq!(backoff(minute, 30), minute * 60);
+    assert_eq!(backoff(minute * 90, 2), minute * 90);
+}
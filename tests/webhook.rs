@@ -0,0 +1,102 @@
+mod common;
+
+use common::WebhookReceiver;
+use orphy::webhook::{EVENT_HEADER, SIGNATURE_HEADER};
+use orphy::{Change, Letter, Webhook, WebhookError};
+use reqwest::Client;
+use serde_json::{Value, json};
+
+fn change() -> Change {
+    let letter: Letter = serde_json::from_value(
+        json!({ "id": "pkg!xyz789", "title": "Hoodie", "status": "shipped" }),
+    )
+    .unwrap();
+    Change::NewEvent {
+        letter,
+        event: serde_json::from_value(json!({ "description": "Accepted" })).unwrap(),
+    }
+}
+
+fn webhook(receiver: &WebhookReceiver) -> Webhook {
+    Webhook {
+        secret: Some(String::from("It's a Secret to Everybody")),
+        retries: Some(1),
+        ..Webhook::new(&receiver.url)
+    }
+}
+
+#[test]
+fn signs_bodies_with_hmac_sha256() {
+    let webhook = Webhook {
+        secret: Some(String::from("It's a Secret to Everybody")),
+        ..Webhook::new("http://localhost")
+    };
+    assert_eq!(
+        webhook.signature(b"Hello, World!").unwrap(),
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17"
+    );
+    assert_eq!(
+        Webhook::new("http://localhost").signature(b"Hello, World!"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn posts_the_change_as_json() {
+    let receiver = WebhookReceiver::start(&[204]);
+    let webhook = webhook(&receiver);
+
+    webhook.send(&Client::new(), &change()).await.unwrap();
+    let deliveries = receiver.deliveries();
+    assert_eq!(deliveries.len(), 1);
+
+    let delivery = &deliveries[0];
+    let body: Value = serde_json::from_str(&delivery.body).unwrap();
+    assert_eq!(body["kind"], "new_event");
+    assert_eq!(body["letter"]["id"], "pkg!xyz789");
+    assert_eq!(body["event"]["description"], "Accepted");
+    assert_eq!(delivery.header("content-type"), Some("application/json"));
+    assert_eq!(
+        delivery.header(&EVENT_HEADER.to_lowercase()),
+        Some("new_event")
+    );
+    assert_eq!(
+        delivery.header(&SIGNATURE_HEADER.to_lowercase()),
+        webhook.signature(delivery.body.as_bytes()).as_deref()
+    );
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let receiver = WebhookReceiver::start(&[503, 200]);
+
+    webhook(&receiver)
+        .send(&Client::new(), &change())
+        .await
+        .unwrap();
+    assert_eq!(receiver.deliveries().len(), 2);
+}
+
+#[tokio::test]
+async fn gives_up_after_the_retries() {
+    let receiver = WebhookReceiver::start(&[500]);
+
+    let err = webhook(&receiver)
+        .send(&Client::new(), &change())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, WebhookError::Status { status, .. } if status == 500));
+    assert_eq!(receiver.deliveries().len(), 2);
+}
+
+#[tokio::test]
+async fn doesnt_retry_rejections() {
+    let receiver = WebhookReceiver::start(&[400, 200]);
+
+    let err = webhook(&receiver)
+        .send(&Client::new(), &change())
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("400"));
+    assert_eq!(receiver.deliveries().len(), 1);
+}